@@ -7,12 +7,22 @@ use std::fmt;
 
 use crate::{rfc5389, rfc5766};
 
+/// The size of the type and length fields of an attribute in bytes.
+pub(crate) const ATTRIBUTE_HEADER_LEN: usize = 4;
+
+/// Returns `len` rounded up to a multiple of 4 (i.e., the length of a value including its padding).
+pub(crate) fn padded_len(len: usize) -> usize {
+    len + (4 - len % 4) % 4
+}
+
+#[allow(dead_code)]
 #[derive(Debug)]
 pub enum MyAttribute {
     Rfc5389(rfc5389::Attribute),
     Rfc5766(rfc5766::Attribute),
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Default)]
 pub struct MyAttributeDecoder {
     rfc5389: rfc5389::AttributeDecoder,
@@ -73,6 +83,7 @@ impl TryTaggedDecode for MyAttributeDecoder {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Default)]
 pub struct MyAttributeEncoder {
     rfc5389: rfc5389::AttributeEncoder,
@@ -558,8 +569,8 @@ pub struct Padding {
     len: usize,
 }
 impl Padding {
-    fn new(value_len: usize) -> Self {
-        let len = (4 - value_len % 4) % 4;
+    pub(crate) fn new(value_len: usize) -> Self {
+        let len = padded_len(value_len) - value_len;
         Padding { buf: [0; 3], len }
     }
 }
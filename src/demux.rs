@@ -0,0 +1,360 @@
+//! Demultiplexing of STUN, TURN `ChannelData` and other protocols sharing the same port.
+//!
+//! Packets are classified by their first byte as described in
+//! [RFC 7983 -- 7. Multiplexing Scheme Updated for RFC 5764].
+//!
+//! [RFC 7983 -- 7. Multiplexing Scheme Updated for RFC 5764]: https://tools.ietf.org/html/rfc7983#section-7
+use crate::message::{DecodedMessage, MessageDecoder};
+use crate::rfc5766::channel_data::{ChannelData, ChannelDataDecoder};
+use crate::Attribute;
+use bytecodec::bytes::RemainingBytesDecoder;
+use bytecodec::{ByteCount, Decode, Eos, ErrorKind, Result};
+use std::fmt;
+
+/// The class of a packet determined by its first byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PacketKind {
+    /// STUN message (`0..=3`).
+    Stun,
+
+    /// ZRTP packet (`16..=19`).
+    Zrtp,
+
+    /// DTLS record (`20..=63`).
+    Dtls,
+
+    /// TURN `ChannelData` message (`64..=79`).
+    ChannelData,
+
+    /// RTP or RTCP packet (`128..=191`).
+    RtpOrRtcp,
+
+    /// Packet that does not belong to any of the above classes.
+    Unknown,
+}
+impl PacketKind {
+    /// Classifies a packet by its first byte.
+    pub fn from_first_byte(b: u8) -> Self {
+        match b {
+            0..=3 => PacketKind::Stun,
+            16..=19 => PacketKind::Zrtp,
+            20..=63 => PacketKind::Dtls,
+            64..=79 => PacketKind::ChannelData,
+            128..=191 => PacketKind::RtpOrRtcp,
+            _ => PacketKind::Unknown,
+        }
+    }
+
+    /// Classifies `packet` by its first byte.
+    ///
+    /// If `packet` is empty, this will return `None`.
+    pub fn classify(packet: &[u8]) -> Option<Self> {
+        packet.first().map(|&b| Self::from_first_byte(b))
+    }
+}
+
+/// A packet decoded by [`DemuxDecoder`].
+#[derive(Debug)]
+pub enum DemuxedPacket<A> {
+    /// STUN message.
+    Stun {
+        /// The decoded message.
+        message: DecodedMessage<A>,
+
+        /// The raw bytes of the message.
+        ///
+        /// They are needed to check the `MESSAGE-INTEGRITY` attribute of the message.
+        bytes: Vec<u8>,
+    },
+
+    /// TURN `ChannelData` message.
+    ChannelData(ChannelData),
+
+    /// Packet of the other protocols.
+    Other {
+        /// The class of the packet.
+        kind: PacketKind,
+
+        /// The raw bytes of the packet.
+        bytes: Vec<u8>,
+    },
+}
+impl<A> DemuxedPacket<A> {
+    /// Returns the class of the packet.
+    pub fn kind(&self) -> PacketKind {
+        match self {
+            DemuxedPacket::Stun { .. } => PacketKind::Stun,
+            DemuxedPacket::ChannelData(_) => PacketKind::ChannelData,
+            DemuxedPacket::Other { kind, .. } => *kind,
+        }
+    }
+}
+
+/// Decoder that demultiplexes datagrams as described in [RFC 7983].
+///
+/// Each datagram must be passed to the decoder with `Eos` reached (e.g., via `DecodeExt::decode_from_bytes`).
+/// STUN messages and `ChannelData` messages are decoded, and
+/// the other packets are returned as raw bytes along with their classes.
+///
+/// If a datagram cannot be decoded (including the case where it has trailing bytes),
+/// the decoder returns an error and discards its intermediate state,
+/// so the following datagrams can be decoded as usual.
+///
+/// [RFC 7983]: https://tools.ietf.org/html/rfc7983
+pub struct DemuxDecoder<A: Attribute> {
+    kind: Option<PacketKind>,
+    message: MessageDecoder<A>,
+    message_bytes: Vec<u8>,
+    channel_data: ChannelDataDecoder,
+    other: RemainingBytesDecoder,
+}
+impl<A: Attribute> DemuxDecoder<A> {
+    /// Makes a new `DemuxDecoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn decode_packet(&mut self, buf: &[u8], eos: Eos) -> Result<usize> {
+        let kind = if let Some(kind) = self.kind {
+            kind
+        } else if let Some(kind) = PacketKind::classify(buf) {
+            self.kind = Some(kind);
+            self.message_bytes.clear();
+            kind
+        } else {
+            track_assert!(!eos.is_reached(), ErrorKind::UnexpectedEos);
+            return Ok(0);
+        };
+        let size = match kind {
+            PacketKind::Stun => {
+                let size = track!(self.message.decode(buf, eos))?;
+                self.message_bytes.extend_from_slice(&buf[..size]);
+                size
+            }
+            PacketKind::ChannelData => track!(self.channel_data.decode(buf, eos))?,
+            _ => track!(self.other.decode(buf, eos))?,
+        };
+        track_assert!(
+            !eos.is_reached() || size == buf.len(),
+            ErrorKind::InvalidInput,
+            "Trailing bytes after the packet";
+            kind, size, buf.len()
+        );
+        Ok(size)
+    }
+
+    fn finish_decoding_packet(&mut self) -> Result<DemuxedPacket<A>> {
+        let kind = track_assert_some!(self.kind.take(), ErrorKind::IncompleteDecoding);
+        match kind {
+            PacketKind::Stun => {
+                let bytes = std::mem::take(&mut self.message_bytes);
+                let message = track!(self.message.finish_decoding())?;
+                Ok(DemuxedPacket::Stun { message, bytes })
+            }
+            PacketKind::ChannelData => {
+                track!(self.channel_data.finish_decoding()).map(DemuxedPacket::ChannelData)
+            }
+            _ => {
+                let bytes = track!(self.other.finish_decoding())?;
+                Ok(DemuxedPacket::Other { kind, bytes })
+            }
+        }
+    }
+}
+impl<A: Attribute> fmt::Debug for DemuxDecoder<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DemuxDecoder {{ kind: {:?}, .. }}", self.kind)
+    }
+}
+impl<A: Attribute> Default for DemuxDecoder<A> {
+    fn default() -> Self {
+        DemuxDecoder {
+            kind: None,
+            message: Default::default(),
+            message_bytes: Vec::new(),
+            channel_data: Default::default(),
+            other: Default::default(),
+        }
+    }
+}
+impl<A: Attribute> Decode for DemuxDecoder<A> {
+    type Item = DemuxedPacket<A>;
+
+    fn decode(&mut self, buf: &[u8], eos: Eos) -> Result<usize> {
+        let result = track!(self.decode_packet(buf, eos));
+        if result.is_err() {
+            // Discards the intermediate state of the broken packet
+            *self = Self::default();
+        }
+        result
+    }
+
+    fn finish_decoding(&mut self) -> Result<Self::Item> {
+        let result = track!(self.finish_decoding_packet());
+        if result.is_err() {
+            *self = Self::default();
+        }
+        result
+    }
+
+    fn requiring_bytes(&self) -> ByteCount {
+        match self.kind {
+            None => ByteCount::Unknown,
+            Some(PacketKind::Stun) => self.message.requiring_bytes(),
+            Some(PacketKind::ChannelData) => self.channel_data.requiring_bytes(),
+            Some(_) => self.other.requiring_bytes(),
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        match self.kind {
+            None => false,
+            Some(PacketKind::Stun) => self.message.is_idle(),
+            Some(PacketKind::ChannelData) => self.channel_data.is_idle(),
+            Some(_) => self.other.is_idle(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rfc5389::attributes::MessageIntegrity;
+    use crate::rfc5389::methods::BINDING;
+    use crate::rfc5389::Attribute as Rfc5389Attribute;
+    use crate::rfc5766::attributes::ChannelNumber;
+    use crate::rfc5766::channel_data::ChannelDataEncoder;
+    use crate::{Message, MessageClass, MessageEncoder, TransactionId};
+    use bytecodec::{DecodeExt, EncodeExt};
+    use trackable::result::TestResult;
+
+    #[test]
+    fn packet_kind_works() {
+        assert_eq!(PacketKind::from_first_byte(0), PacketKind::Stun);
+        assert_eq!(PacketKind::from_first_byte(3), PacketKind::Stun);
+        assert_eq!(PacketKind::from_first_byte(4), PacketKind::Unknown);
+        assert_eq!(PacketKind::from_first_byte(16), PacketKind::Zrtp);
+        assert_eq!(PacketKind::from_first_byte(20), PacketKind::Dtls);
+        assert_eq!(PacketKind::from_first_byte(63), PacketKind::Dtls);
+        assert_eq!(PacketKind::from_first_byte(64), PacketKind::ChannelData);
+        assert_eq!(PacketKind::from_first_byte(79), PacketKind::ChannelData);
+        assert_eq!(PacketKind::from_first_byte(80), PacketKind::Unknown);
+        assert_eq!(PacketKind::from_first_byte(128), PacketKind::RtpOrRtcp);
+        assert_eq!(PacketKind::from_first_byte(191), PacketKind::RtpOrRtcp);
+        assert_eq!(PacketKind::from_first_byte(192), PacketKind::Unknown);
+        assert_eq!(PacketKind::classify(&[]), None);
+    }
+
+    #[test]
+    fn demux_decoder_works() -> TestResult {
+        let mut decoder = DemuxDecoder::<Rfc5389Attribute>::new();
+
+        let message = Message::<Rfc5389Attribute>::new(
+            MessageClass::Request,
+            BINDING,
+            TransactionId::new([0; 12]),
+        );
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+        match track!(decoder.decode_from_bytes(&bytes))? {
+            DemuxedPacket::Stun {
+                message: Ok(message),
+                bytes: decoded_bytes,
+            } => {
+                assert_eq!(message.method(), BINDING);
+                assert_eq!(decoded_bytes, bytes);
+            }
+            packet => panic!("{:?}", packet),
+        }
+
+        let channel_data = track!(ChannelData::new(ChannelNumber::min(), vec![1, 2, 3]))?;
+        let bytes = track!(ChannelDataEncoder::new().encode_into_bytes(channel_data.clone()))?;
+        match track!(decoder.decode_from_bytes(&bytes))? {
+            DemuxedPacket::ChannelData(decoded) => assert_eq!(decoded, channel_data),
+            packet => panic!("{:?}", packet),
+        }
+
+        let dtls = [22, 0xfe, 0xfd, 0, 0];
+        let packet = track!(decoder.decode_from_bytes(&dtls))?;
+        assert_eq!(packet.kind(), PacketKind::Dtls);
+        match packet {
+            DemuxedPacket::Other { bytes, .. } => assert_eq!(bytes, dtls),
+            packet => panic!("{:?}", packet),
+        }
+
+        let rtp = [0x80, 0x60, 0, 1];
+        let packet = track!(decoder.decode_from_bytes(&rtp))?;
+        assert_eq!(packet.kind(), PacketKind::RtpOrRtcp);
+
+        assert!(decoder.decode_from_bytes(&[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn demuxed_stun_message_integrity_can_be_checked() -> TestResult {
+        let mut message = Message::<Rfc5389Attribute>::new(
+            MessageClass::Request,
+            BINDING,
+            TransactionId::new([0; 12]),
+        );
+        message.add_attribute(track!(MessageIntegrity::new_with_key(&message, b"key"))?);
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+
+        let mut decoder = DemuxDecoder::<Rfc5389Attribute>::new();
+        for _ in 0..2 {
+            match track!(decoder.decode_from_bytes(&bytes))? {
+                DemuxedPacket::Stun {
+                    message: Ok(message),
+                    bytes,
+                } => {
+                    let mi = message
+                        .get_attribute::<MessageIntegrity>()
+                        .expect("never fails");
+                    assert!(mi.check_with_key(&bytes, b"key").is_ok());
+                    assert!(mi.check_with_key(&bytes, b"foo").is_err());
+                }
+                packet => panic!("{:?}", packet),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn demux_decoder_recovers_from_broken_packets() -> TestResult {
+        let message = Message::<Rfc5389Attribute>::new(
+            MessageClass::Request,
+            BINDING,
+            TransactionId::new([0; 12]),
+        );
+        let stun = track!(MessageEncoder::new().encode_into_bytes(message))?;
+        let channel_data = track!(ChannelData::new(ChannelNumber::min(), vec![1, 2, 3]))?;
+        let channel_data = track!(ChannelDataEncoder::new().encode_into_bytes(channel_data))?;
+        let rtp = [0x80, 0x60, 0, 1];
+
+        // Invalid magic cookie
+        let mut bad_cookie = stun.clone();
+        bad_cookie[4] ^= 0xFF;
+
+        // Truncated `ChannelData`
+        let truncated = &channel_data[..channel_data.len() - 1];
+
+        // Trailing bytes after a STUN message
+        let mut trailing = stun.clone();
+        trailing.extend_from_slice(&[0; 4]);
+
+        let mut decoder = DemuxDecoder::<Rfc5389Attribute>::new();
+        for broken in [&bad_cookie[..], truncated, &trailing[..]] {
+            assert!(decoder.decode_from_bytes(broken).is_err());
+            let packet = track!(decoder.decode_from_bytes(&rtp))?;
+            assert_eq!(packet.kind(), PacketKind::RtpOrRtcp);
+
+            assert!(decoder.decode_from_bytes(broken).is_err());
+            let packet = track!(decoder.decode_from_bytes(&stun))?;
+            assert_eq!(packet.kind(), PacketKind::Stun);
+
+            assert!(decoder.decode_from_bytes(broken).is_err());
+            let packet = track!(decoder.decode_from_bytes(&channel_data))?;
+            assert_eq!(packet.kind(), PacketKind::ChannelData);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,409 @@
+//! Verification and appending of `MESSAGE-INTEGRITY`, `MESSAGE-INTEGRITY-SHA256` and `FINGERPRINT`
+//! on encoded STUN messages.
+//!
+//! The functions in this module work directly on the bytes of messages.
+//! So, for example, a proxy can verify and re-sign a forwarded message
+//! (by using [`strip_integrity_attributes`] and `append_*` functions)
+//! without decoding it into a [`Message`] and encoding it again.
+//!
+//! [`Message`]: crate::Message
+//!
+//! # Examples
+//!
+//! ```
+//! use bytecodec::EncodeExt;
+//! use stun_codec::integrity;
+//! use stun_codec::rfc5389::{methods::BINDING, Attribute};
+//! use stun_codec::{Message, MessageClass, MessageEncoder, TransactionId};
+//!
+//! let message = Message::<Attribute>::new(MessageClass::Request, BINDING, TransactionId::new([3; 12]));
+//! let mut bytes = MessageEncoder::new().encode_into_bytes(message).unwrap();
+//!
+//! integrity::append_message_integrity(&mut bytes, b"key").unwrap();
+//! integrity::append_fingerprint(&mut bytes).unwrap();
+//!
+//! assert!(integrity::verify_message_integrity(&bytes, b"key").is_ok());
+//! assert!(integrity::verify_message_integrity(&bytes, b"foo").is_err());
+//! assert!(integrity::verify_fingerprint(&bytes).is_ok());
+//!
+//! // Re-signs the message with another key
+//! integrity::strip_integrity_attributes(&mut bytes).unwrap();
+//! integrity::append_message_integrity(&mut bytes, b"bar").unwrap();
+//! assert!(integrity::verify_message_integrity(&bytes, b"bar").is_ok());
+//! ```
+use crate::attribute::{AttributeType, ATTRIBUTE_HEADER_LEN};
+use crate::message::MessageHeader;
+use crate::message_ref::{MessageRef, RawAttributeRef};
+use crate::rfc5389::attributes::{Fingerprint, FingerprintDigest, MessageIntegrity};
+use crate::rfc8489::attributes::MessageIntegritySha256;
+use bytecodec::{ErrorKind, Result};
+use byteorder::{BigEndian, ByteOrder};
+use subtle::ConstantTimeEq;
+
+const FINGERPRINT_LEN: usize = 4;
+
+/// Verifies the first `MESSAGE-INTEGRITY` attribute of `message` by using `key`.
+///
+/// The HMAC-SHA1 values are compared in constant time.
+///
+/// # Errors
+///
+/// If `message` is malformed, it does not contain a `MESSAGE-INTEGRITY` attribute or
+/// the HMAC-SHA1 value of the attribute is not valid for `key`,
+/// this will return an `ErrorKind::InvalidInput` error.
+pub fn verify_message_integrity(message: &[u8], key: &[u8]) -> Result<()> {
+    let attr = track!(find_attribute(message, MessageIntegrity::CODEPOINT))?;
+    let expected = track!(MessageIntegrity::generate_hmac_token(
+        key,
+        message,
+        attr.offset()
+    ))?;
+    track_assert!(
+        bool::from(attr.value().ct_eq(&expected[..])),
+        ErrorKind::InvalidInput,
+        "MESSAGE-INTEGRITY mismatch"
+    );
+    Ok(())
+}
+
+/// Verifies the first `MESSAGE-INTEGRITY-SHA256` attribute of `message` by using `key`.
+///
+/// If the HMAC-SHA256 value of the attribute is truncated,
+/// only the leading bytes of the expected value are compared.
+/// The values are compared in constant time.
+///
+/// # Errors
+///
+/// If `message` is malformed, it does not contain a `MESSAGE-INTEGRITY-SHA256` attribute or
+/// the HMAC-SHA256 value of the attribute is not valid for `key`,
+/// this will return an `ErrorKind::InvalidInput` error.
+pub fn verify_message_integrity_sha256(message: &[u8], key: &[u8]) -> Result<()> {
+    let attr = track!(find_attribute(message, MessageIntegritySha256::CODEPOINT))?;
+    let hmac_len = attr.value().len();
+    track!(MessageIntegritySha256::check_hmac_len(hmac_len))?;
+    let expected = track!(MessageIntegritySha256::generate_hmac_token(
+        key,
+        message,
+        attr.offset(),
+        hmac_len
+    ))?;
+    track_assert!(
+        bool::from(attr.value().ct_eq(&expected[..hmac_len])),
+        ErrorKind::InvalidInput,
+        "MESSAGE-INTEGRITY-SHA256 mismatch"
+    );
+    Ok(())
+}
+
+/// Verifies the `FINGERPRINT` attribute of `message`.
+///
+/// # Errors
+///
+/// If `message` is malformed, its last attribute is not `FINGERPRINT` or
+/// the CRC-32 value of the attribute is not valid,
+/// this will return an `ErrorKind::InvalidInput` error.
+pub fn verify_fingerprint(message: &[u8]) -> Result<()> {
+    let message = track!(MessageRef::new(message))?;
+    let attr = track_assert_some!(message.raw_attributes().last(), ErrorKind::InvalidInput);
+    track_assert_eq!(
+        attr.get_type(),
+        AttributeType::new(Fingerprint::CODEPOINT),
+        ErrorKind::InvalidInput
+    );
+    track_assert_eq!(attr.value().len(), FINGERPRINT_LEN, ErrorKind::InvalidInput);
+
+    let mut fingerprint = FingerprintDigest::new(attr.offset());
+    fingerprint.update(message.as_bytes());
+    let expected = fingerprint.finalize();
+    track_assert_eq!(
+        BigEndian::read_u32(attr.value()),
+        expected,
+        ErrorKind::InvalidInput
+    );
+    Ok(())
+}
+
+/// Appends a `MESSAGE-INTEGRITY` attribute calculated by using `key` to `message`.
+///
+/// The length field of the header of `message` is updated accordingly.
+///
+/// # Errors
+///
+/// If `message` is malformed or it already has a `MESSAGE-INTEGRITY`, `MESSAGE-INTEGRITY-SHA256` or
+/// `FINGERPRINT` attribute, this will return an `ErrorKind::InvalidInput` error.
+pub fn append_message_integrity(message: &mut Vec<u8>, key: &[u8]) -> Result<()> {
+    let offset = track!(check_appendable(message, MessageIntegrity::CODEPOINT))?;
+    let hmac = track!(MessageIntegrity::generate_hmac_token(key, message, offset))?;
+    track!(append_attribute(
+        message,
+        MessageIntegrity::CODEPOINT,
+        &hmac
+    ))
+}
+
+/// Appends a `MESSAGE-INTEGRITY-SHA256` attribute calculated by using `key` to `message`.
+///
+/// The HMAC-SHA256 value is truncated to the first `hmac_len` bytes.
+/// The length field of the header of `message` is updated accordingly.
+///
+/// # Errors
+///
+/// If `message` is malformed, it already has a `MESSAGE-INTEGRITY-SHA256` or `FINGERPRINT` attribute or
+/// `hmac_len` is not a multiple of `4` in the range of `16..=32`,
+/// this will return an `ErrorKind::InvalidInput` error.
+pub fn append_message_integrity_sha256(
+    message: &mut Vec<u8>,
+    key: &[u8],
+    hmac_len: usize,
+) -> Result<()> {
+    track!(MessageIntegritySha256::check_hmac_len(hmac_len))?;
+    let offset = track!(check_appendable(message, MessageIntegritySha256::CODEPOINT))?;
+    let hmac = track!(MessageIntegritySha256::generate_hmac_token(
+        key, message, offset, hmac_len
+    ))?;
+    track!(append_attribute(
+        message,
+        MessageIntegritySha256::CODEPOINT,
+        &hmac[..hmac_len]
+    ))
+}
+
+/// Appends a `FINGERPRINT` attribute to `message`.
+///
+/// The length field of the header of `message` is updated accordingly.
+///
+/// # Errors
+///
+/// If `message` is malformed or it already has a `FINGERPRINT` attribute,
+/// this will return an `ErrorKind::InvalidInput` error.
+pub fn append_fingerprint(message: &mut Vec<u8>) -> Result<()> {
+    let offset = track!(check_appendable(message, Fingerprint::CODEPOINT))?;
+    track!(append_attribute(
+        message,
+        Fingerprint::CODEPOINT,
+        &[0; FINGERPRINT_LEN]
+    ))?;
+
+    let mut fingerprint = FingerprintDigest::new(offset);
+    fingerprint.update(message);
+    let crc32 = fingerprint.finalize();
+    BigEndian::write_u32(&mut message[offset + ATTRIBUTE_HEADER_LEN..], crc32);
+    Ok(())
+}
+
+/// Removes the first `MESSAGE-INTEGRITY`, `MESSAGE-INTEGRITY-SHA256` or `FINGERPRINT` attribute
+/// and all the attributes following it from `message`.
+///
+/// The length field of the header of `message` is updated accordingly.
+/// If `message` has none of the attributes, it is left unchanged.
+///
+/// # Errors
+///
+/// If `message` is malformed, this will return an `ErrorKind::InvalidInput` error.
+pub fn strip_integrity_attributes(message: &mut Vec<u8>) -> Result<()> {
+    let offset = track!(MessageRef::new(message))?
+        .raw_attributes()
+        .find(|a| {
+            matches!(
+                a.get_type().as_u16(),
+                MessageIntegrity::CODEPOINT
+                    | MessageIntegritySha256::CODEPOINT
+                    | Fingerprint::CODEPOINT
+            )
+        })
+        .map(|a| a.offset());
+    if let Some(offset) = offset {
+        message.truncate(offset);
+        let message_len = offset - MessageHeader::SIZE;
+        BigEndian::write_u16(&mut message[2..4], message_len as u16);
+    }
+    Ok(())
+}
+
+fn find_attribute(message: &[u8], codepoint: u16) -> Result<RawAttributeRef<'_>> {
+    let message = track!(MessageRef::new(message))?;
+    let attr = track_assert_some!(
+        message.get_raw_attribute(AttributeType::new(codepoint)),
+        ErrorKind::InvalidInput,
+        "No such attribute: codepoint=0x{:04x}",
+        codepoint
+    );
+    Ok(attr)
+}
+
+fn check_appendable(message: &[u8], codepoint: u16) -> Result<usize> {
+    let message = track!(MessageRef::new(message))?;
+    let has = |codepoint| {
+        message
+            .get_raw_attribute(AttributeType::new(codepoint))
+            .is_some()
+    };
+    track_assert!(
+        !has(Fingerprint::CODEPOINT),
+        ErrorKind::InvalidInput,
+        "No attribute can follow FINGERPRINT"
+    );
+    track_assert!(
+        !has(codepoint),
+        ErrorKind::InvalidInput,
+        "Duplicate attribute: codepoint=0x{:04x}",
+        codepoint
+    );
+    if codepoint == MessageIntegrity::CODEPOINT {
+        track_assert!(
+            !has(MessageIntegritySha256::CODEPOINT),
+            ErrorKind::InvalidInput,
+            "MESSAGE-INTEGRITY cannot follow MESSAGE-INTEGRITY-SHA256"
+        );
+    }
+    Ok(message.as_bytes().len())
+}
+
+fn append_attribute(message: &mut Vec<u8>, codepoint: u16, value: &[u8]) -> Result<()> {
+    let message_len = message.len() - MessageHeader::SIZE + ATTRIBUTE_HEADER_LEN + value.len();
+    track_assert!(message_len <= 0xFFFF, ErrorKind::InvalidInput; message_len);
+
+    BigEndian::write_u16(&mut message[2..4], message_len as u16);
+    message.extend_from_slice(&codepoint.to_be_bytes());
+    message.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    message.extend_from_slice(value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rfc5389::attributes::Software;
+    use crate::rfc5389::methods::BINDING;
+    use crate::test_util::TestAttribute;
+    use crate::{Message, MessageClass, MessageDecoder, MessageEncoder, TransactionId};
+    use bytecodec::{DecodeExt, EncodeExt};
+    use trackable::result::TestResult;
+
+    fn message() -> Message<TestAttribute> {
+        let mut message = Message::new(MessageClass::Request, BINDING, TransactionId::new([3; 12]));
+        message.add_attribute(Software::new("foo".to_owned()).expect("never fails"));
+        message
+    }
+
+    #[test]
+    fn appended_attributes_are_same_as_encoded_ones() -> TestResult {
+        let mut expected = message();
+        expected.add_attribute(track!(MessageIntegrity::new_with_key(&expected, b"key"))?);
+        expected.add_attribute(track!(MessageIntegritySha256::new_with_key(
+            &expected, b"key", 16
+        ))?);
+        expected.add_attribute(Fingerprint::placeholder());
+        let expected = track!(MessageEncoder::new().encode_into_bytes(expected))?;
+
+        let mut bytes = track!(MessageEncoder::new().encode_into_bytes(message()))?;
+        track!(append_message_integrity(&mut bytes, b"key"))?;
+        track!(append_message_integrity_sha256(&mut bytes, b"key", 16))?;
+        track!(append_fingerprint(&mut bytes))?;
+        assert_eq!(bytes, expected);
+
+        let decoded = track!(MessageDecoder::<TestAttribute>::new().decode_from_bytes(&bytes))?;
+        assert!(decoded.is_ok());
+
+        // Nothing can follow `FINGERPRINT`
+        assert!(append_fingerprint(&mut bytes.clone()).is_err());
+        assert!(append_message_integrity(&mut bytes.clone(), b"key").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn integrity_attributes_cannot_be_duplicated() -> TestResult {
+        let mut bytes = track!(MessageEncoder::new().encode_into_bytes(message()))?;
+        track!(append_message_integrity(&mut bytes, b"key"))?;
+        assert!(append_message_integrity(&mut bytes.clone(), b"key").is_err());
+
+        track!(append_message_integrity_sha256(&mut bytes, b"key", 32))?;
+        assert!(append_message_integrity_sha256(&mut bytes.clone(), b"key", 32).is_err());
+
+        // `MESSAGE-INTEGRITY` cannot follow `MESSAGE-INTEGRITY-SHA256`
+        let mut bytes = track!(MessageEncoder::new().encode_into_bytes(message()))?;
+        track!(append_message_integrity_sha256(&mut bytes, b"key", 32))?;
+        assert!(append_message_integrity(&mut bytes, b"key").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_fingerprint_is_rejected() -> TestResult {
+        let original = track!(MessageEncoder::new().encode_into_bytes(message()))?;
+
+        // Missing
+        assert!(verify_fingerprint(&original).is_err());
+
+        // Truncated value
+        let mut bytes = original.clone();
+        track!(append_attribute(
+            &mut bytes,
+            Fingerprint::CODEPOINT,
+            &[0; 2]
+        ))?;
+        bytes.extend_from_slice(&[0; 2]); // padding
+        bytes[3] += 2;
+        track!(MessageRef::new(&bytes))?;
+        assert!(verify_fingerprint(&bytes).is_err());
+
+        // Not the last attribute
+        let mut bytes = original.clone();
+        track!(append_fingerprint(&mut bytes))?;
+        track!(verify_fingerprint(&bytes))?;
+        track!(append_attribute(&mut bytes, Software::CODEPOINT, b"bar!"))?;
+        track!(MessageRef::new(&bytes))?;
+        assert!(verify_fingerprint(&bytes).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn strip_integrity_attributes_works() -> TestResult {
+        let original = track!(MessageEncoder::new().encode_into_bytes(message()))?;
+
+        let mut bytes = original.clone();
+        track!(strip_integrity_attributes(&mut bytes))?;
+        assert_eq!(bytes, original);
+
+        track!(append_message_integrity(&mut bytes, b"key"))?;
+        track!(append_message_integrity_sha256(&mut bytes, b"key", 16))?;
+        track!(append_fingerprint(&mut bytes))?;
+        track!(strip_integrity_attributes(&mut bytes))?;
+        assert_eq!(bytes, original);
+
+        // Re-signs a message with another key
+        track!(append_message_integrity(&mut bytes, b"key"))?;
+        track!(append_fingerprint(&mut bytes))?;
+        track!(strip_integrity_attributes(&mut bytes))?;
+        track!(append_message_integrity(&mut bytes, b"foo"))?;
+        track!(append_fingerprint(&mut bytes))?;
+        track!(verify_message_integrity(&bytes, b"foo"))?;
+        track!(verify_fingerprint(&bytes))?;
+        assert!(verify_message_integrity(&bytes, b"key").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn verification_works() -> TestResult {
+        let mut bytes = track!(MessageEncoder::new().encode_into_bytes(message()))?;
+        assert!(verify_message_integrity(&bytes, b"key").is_err());
+        assert!(verify_fingerprint(&bytes).is_err());
+
+        track!(append_message_integrity(&mut bytes, b"key"))?;
+        track!(append_message_integrity_sha256(&mut bytes, b"key", 32))?;
+        track!(append_fingerprint(&mut bytes))?;
+        track!(verify_message_integrity(&bytes, b"key"))?;
+        track!(verify_message_integrity_sha256(&bytes, b"key"))?;
+        track!(verify_fingerprint(&bytes))?;
+
+        assert!(verify_message_integrity(&bytes, b"foo").is_err());
+        assert!(verify_message_integrity_sha256(&bytes, b"foo").is_err());
+
+        // Tampered bytes
+        let mut broken = bytes.clone();
+        broken[24] = b'F';
+        assert!(verify_message_integrity(&broken, b"key").is_err());
+        assert!(verify_message_integrity_sha256(&broken, b"key").is_err());
+        assert!(verify_fingerprint(&broken).is_err());
+        Ok(())
+    }
+}
@@ -69,18 +69,28 @@
 //!
 //! # References
 //!
+//! - [RFC 3489 - STUN - Simple Traversal of User Datagram Protocol (UDP) Through Network Address Translators (NATs)][RFC 3489]
 //! - [RFC 5389 - Session Traversal Utilities for NAT (STUN)][RFC 5389]
 //! - [RFC 5769 - Test Vectors for Session Traversal Utilities for NAT (STUN)][RFC 5769]
 //! - [RFC 5245 - Interactive Connectivity Establishment (ICE)][RFC 5245]
 //! - [RFC 5780 - NAT Behavior Discovery Using Session Traversal Utilities for NAT][RFC 5780]
+//! - [RFC 6062 - Traversal Using Relays around NAT (TURN) Extensions for TCP Allocations][RFC 6062]
+//! - [RFC 7635 - Session Traversal Utilities for NAT (STUN) Extension for Third-Party Authorization][RFC 7635]
+//! - [RFC 7983 - Multiplexing Scheme Updates for Secure Real-time Transport Protocol (SRTP) Extension for Datagram Transport Layer Security (DTLS)][RFC 7983]
 //! - [RFC 8016 - Mobility with Traversal Using Relays around NAT (TURN)][RFC 8016]
+//! - [RFC 8489 - Session Traversal Utilities for NAT (STUN)][RFC 8489]
 //! - [RFC 8656 - Traversal Using Relays around NAT (TURN): Relay Extensions to Session Traversal Utilities for NAT (STUN)][RFC 8656]
 //!
+//! [RFC 3489]: https://tools.ietf.org/html/rfc3489
 //! [RFC 5389]: https://tools.ietf.org/html/rfc5389
 //! [RFC 5769]: https://tools.ietf.org/html/rfc5769
 //! [RFC 5245]: https://tools.ietf.org/html/rfc5245
 //! [RFC 5780]: https://tools.ietf.org/html/rfc5780
+//! [RFC 6062]: https://tools.ietf.org/html/rfc6062
+//! [RFC 7635]: https://tools.ietf.org/html/rfc7635
+//! [RFC 7983]: https://tools.ietf.org/html/rfc7983
 //! [RFC 8016]: https://tools.ietf.org/html/rfc8016
+//! [RFC 8489]: https://tools.ietf.org/html/rfc8489
 //! [RFC 8656]: https://tools.ietf.org/html/rfc8656
 #![warn(missing_docs)]
 
@@ -94,7 +104,9 @@ pub use attribute::{
 };
 pub use message::{
     BrokenMessage, DecodedMessage, Message, MessageClass, MessageDecoder, MessageEncoder,
+    MessageHeader,
 };
+pub use message_ref::{MessageRef, RawAttributeRef, RawAttributesRef};
 pub use method::Method;
 pub use transaction_id::TransactionId;
 
@@ -103,18 +115,30 @@ pub use transaction_id::TransactionId;
 pub mod macros;
 
 pub mod convert;
+pub mod demux;
+pub mod integrity;
 pub mod net;
+pub mod rfc3489;
 pub mod rfc5245;
 pub mod rfc5389;
 pub mod rfc5766;
 pub mod rfc5780;
+pub mod rfc6062;
+pub mod rfc7635;
 pub mod rfc8016;
+pub mod rfc8489;
 pub mod rfc8656;
+pub mod stream;
+#[cfg(feature = "tokio")]
+pub mod tokio;
 
 mod attribute;
 mod constants;
 mod message;
+mod message_ref;
 mod method;
+#[cfg(test)]
+mod test_util;
 mod transaction_id;
 
 #[cfg(test)]
@@ -182,7 +206,7 @@ mod tests {
         // TEST: `MessageIntegrity`
         let password = "VOkJxbRl1RmTxUk/WvJxBt";
         get_attr!(message, MessageIntegrity)
-            .check_short_term_credential(password)
+            .check_short_term_credential(&input, password)
             .unwrap();
 
         // TEST: `Fingerprint`
@@ -211,7 +235,7 @@ mod tests {
         // TEST: `MessageIntegrity`
         let password = "VOkJxbRl1RmTxUk/WvJxBt";
         get_attr!(message, MessageIntegrity)
-            .check_short_term_credential(password)
+            .check_short_term_credential(&input, password)
             .unwrap();
 
         // TEST: `XorMappedAddress` (IPv4)
@@ -247,7 +271,7 @@ mod tests {
         // TEST: `MessageIntegrity`
         let password = "VOkJxbRl1RmTxUk/WvJxBt";
         get_attr!(message, MessageIntegrity)
-            .check_short_term_credential(password)
+            .check_short_term_credential(&input, password)
             .unwrap();
 
         // TEST: `XorMappedAddress` (IPv6)
@@ -287,9 +311,12 @@ mod tests {
         // TEST: `MessageIntegrity`
         let username = get_attr!(message, Username);
         let realm = get_attr!(message, Realm);
-        let password = "TheMatrIX"; // TODO: Test before SASLprep version
+        let password = "The\u{00AD}M\u{00AA}tr\u{2168}"; // "TheMatrIX" after SASLprep
+        get_attr!(message, MessageIntegrity)
+            .check_long_term_credential(&input, username, realm, password)
+            .unwrap();
         get_attr!(message, MessageIntegrity)
-            .check_long_term_credential(username, realm, password)
+            .check_long_term_credential(&input, username, realm, "TheMatrIX")
             .unwrap();
 
         Ok(())
@@ -16,6 +16,7 @@ macro_rules! define_attribute_enums {
             }
         })*
         $(impl $crate::convert::TryAsRef<$variant> for $attr {
+            #[allow(irrefutable_let_patterns)]
             fn try_as_ref(&self) -> Option<&$variant> {
                 if let $attr::$variant(a) = self {
                     Some(a)
@@ -3,12 +3,14 @@ use crate::attribute::{
 };
 use crate::constants::MAGIC_COOKIE;
 use crate::convert::TryAsRef;
+use crate::rfc5389::attributes::{Fingerprint, FingerprintDigest};
 use crate::{Method, TransactionId};
 use bytecodec::bytes::{BytesEncoder, CopyableBytesDecoder};
-use bytecodec::combinator::{Collect, Length, Peekable, PreEncode, Repeat};
-use bytecodec::fixnum::{U16beDecoder, U16beEncoder, U32beDecoder, U32beEncoder};
+use bytecodec::combinator::{Length, Peekable};
+use bytecodec::fixnum::{U16beDecoder, U32beDecoder};
 use bytecodec::{ByteCount, Decode, Encode, Eos, Error, ErrorKind, Result, SizedEncode};
-use std::{fmt, vec};
+use byteorder::{BigEndian, ByteOrder};
+use std::{cmp, fmt, mem};
 use trackable::error::ErrorKindExt;
 
 /// Message decoded by [`MessageDecoder`].
@@ -170,8 +172,11 @@ impl fmt::Display for MessageClass {
 pub struct Message<A> {
     class: MessageClass,
     method: Method,
+    magic_cookie: u32,
     transaction_id: TransactionId,
     attributes: Vec<LosslessAttribute<A>>,
+    computed_fingerprint: Option<u32>,
+    attribute_offset: Option<usize>,
 }
 impl<A: Attribute> Message<A> {
     /// Makes a new `Message` instance.
@@ -179,8 +184,36 @@ impl<A: Attribute> Message<A> {
         Message {
             class,
             method,
+            magic_cookie: MAGIC_COOKIE,
             transaction_id,
             attributes: Vec::new(),
+            computed_fingerprint: None,
+            attribute_offset: None,
+        }
+    }
+
+    /// Makes a new [RFC 3489] classic STUN message that has the 128-bit transaction ID.
+    ///
+    /// In [RFC 3489], the magic cookie field was part of the transaction ID.
+    /// So the first four bytes of `transaction_id` are encoded into the field.
+    ///
+    /// [RFC 3489]: https://tools.ietf.org/html/rfc3489
+    pub fn new_classic(class: MessageClass, method: Method, transaction_id: [u8; 16]) -> Self {
+        let mut id = [0; 12];
+        id.copy_from_slice(&transaction_id[4..]);
+        Message {
+            class,
+            method,
+            magic_cookie: u32::from_be_bytes([
+                transaction_id[0],
+                transaction_id[1],
+                transaction_id[2],
+                transaction_id[3],
+            ]),
+            transaction_id: TransactionId::new(id),
+            attributes: Vec::new(),
+            computed_fingerprint: None,
+            attribute_offset: None,
         }
     }
 
@@ -195,10 +228,36 @@ impl<A: Attribute> Message<A> {
     }
 
     /// Returns the transaction ID of the message.
+    ///
+    /// Note that if the message is an [RFC 3489] classic STUN message,
+    /// this only returns the last 96 bits of the transaction ID.
+    /// Use [`Message::classic_transaction_id`] to get the full transaction ID.
+    ///
+    /// [RFC 3489]: https://tools.ietf.org/html/rfc3489
     pub fn transaction_id(&self) -> TransactionId {
         self.transaction_id
     }
 
+    /// Returns `true` if the message is an [RFC 3489] classic STUN message
+    /// (i.e., the magic cookie field does not contain `0x2112A442`).
+    ///
+    /// [RFC 3489]: https://tools.ietf.org/html/rfc3489
+    pub fn is_classic(&self) -> bool {
+        self.magic_cookie != MAGIC_COOKIE
+    }
+
+    /// Returns the 128-bit transaction ID of [RFC 3489].
+    ///
+    /// The result consists of the magic cookie field and the transaction ID field of the message.
+    ///
+    /// [RFC 3489]: https://tools.ietf.org/html/rfc3489
+    pub fn classic_transaction_id(&self) -> [u8; 16] {
+        let mut id = [0; 16];
+        id[..4].copy_from_slice(&self.magic_cookie.to_be_bytes());
+        id[4..].copy_from_slice(self.transaction_id.as_bytes());
+        id
+    }
+
     /// Returns a reference to the first occurance of `T` attribute in the attributes of the message.
     ///
     /// If there is no such attribute, this method will return `None`.
@@ -228,6 +287,26 @@ impl<A: Attribute> Message<A> {
         self.attributes
             .push(LosslessAttribute::new(attribute.into()));
     }
+
+    /// Returns the `FINGERPRINT` value calculated from the received bytes while decoding the message.
+    pub(crate) fn computed_fingerprint(&self) -> Option<u32> {
+        self.computed_fingerprint
+    }
+
+    pub(crate) fn set_computed_fingerprint(&mut self, crc32: Option<u32>) {
+        self.computed_fingerprint = crc32;
+    }
+
+    /// Returns the offset (from the head of the received bytes) of the attribute being decoded.
+    ///
+    /// This is available only while [`Attribute::after_decode`] is being called.
+    pub(crate) fn attribute_offset(&self) -> Option<usize> {
+        self.attribute_offset
+    }
+
+    pub(crate) fn set_attribute_offset(&mut self, offset: Option<usize>) {
+        self.attribute_offset = offset;
+    }
 }
 
 /// STUN message of which [`MessageDecoder`] could not decode the attribute part.
@@ -266,15 +345,143 @@ impl From<BrokenMessage> for Error {
     }
 }
 
+/// The 20-byte header of a STUN message.
+///
+/// This can be used to inspect a packet (e.g., to route it by the transaction ID)
+/// before deciding whether to decode the whole message.
+///
+/// # Examples
+///
+/// ```
+/// use stun_codec::rfc5389::methods::BINDING;
+/// use stun_codec::{MessageClass, MessageHeader};
+///
+/// let packet = [
+///     0x00, 0x01, 0x00, 0x00, 0x21, 0x12, 0xa4, 0x42, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+///     0x08, 0x09, 0x0a, 0x0b, 0x0c,
+/// ];
+/// assert!(MessageHeader::looks_like_stun(&packet));
+///
+/// let header = MessageHeader::peek(&packet).unwrap();
+/// assert_eq!(header.class(), MessageClass::Request);
+/// assert_eq!(header.method(), BINDING);
+/// assert_eq!(header.message_len(), 0);
+/// assert_eq!(header.transaction_id().as_bytes()[0], 0x01);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MessageHeader {
+    class: MessageClass,
+    method: Method,
+    message_len: u16,
+    magic_cookie: u32,
+    transaction_id: TransactionId,
+}
+impl MessageHeader {
+    /// The size of a STUN message header in bytes.
+    pub const SIZE: usize = 20;
+
+    /// Parses the header at the head of `bytes`.
+    ///
+    /// The bytes following the header are not inspected.
+    /// Note that this does not check the magic cookie so that [RFC 3489] classic headers can also be parsed.
+    ///
+    /// # Errors
+    ///
+    /// If `bytes` is shorter than `MessageHeader::SIZE` or the first two bits of it are not zeros,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    ///
+    /// [RFC 3489]: https://tools.ietf.org/html/rfc3489
+    pub fn peek(bytes: &[u8]) -> Result<Self> {
+        track_assert!(bytes.len() >= Self::SIZE, ErrorKind::InvalidInput; bytes.len());
+        let Type { class, method } =
+            track!(Type::from_u16(u16::from_be_bytes([bytes[0], bytes[1]])))?;
+        let message_len = u16::from_be_bytes([bytes[2], bytes[3]]);
+        let magic_cookie = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+        let mut transaction_id = [0; 12];
+        transaction_id.copy_from_slice(&bytes[8..Self::SIZE]);
+        Ok(MessageHeader {
+            class,
+            method,
+            message_len,
+            magic_cookie,
+            transaction_id: TransactionId::new(transaction_id),
+        })
+    }
+
+    /// Returns `true` if `packet` looks like a STUN message.
+    ///
+    /// That is, `packet` starts with a header that has the magic cookie and
+    /// the message length of which is a multiple of 4 and matches the length of the rest of `packet`.
+    pub fn looks_like_stun(packet: &[u8]) -> bool {
+        Self::peek(packet)
+            .is_ok_and(|header| header.is_plausible() && header.message_size() == packet.len())
+    }
+
+    /// Returns `true` if the header has the magic cookie and the message length of it is a multiple of 4.
+    pub(crate) fn is_plausible(&self) -> bool {
+        self.has_magic_cookie() && self.message_len % 4 == 0
+    }
+
+    /// Returns `true` if `bytes` can be the first bytes of a plausible header.
+    ///
+    /// Only the available bytes (up to the magic cookie field) are inspected.
+    pub(crate) fn is_plausible_prefix(bytes: &[u8]) -> bool {
+        let cookie = MAGIC_COOKIE.to_be_bytes();
+        let n = cmp::min(bytes.len(), 8);
+        (n < 1 || bytes[0] >> 6 == 0)
+            && (n < 4 || bytes[3] % 4 == 0)
+            && (n <= 4 || bytes[4..n] == cookie[..n - 4])
+    }
+
+    /// Returns the class of the message.
+    pub fn class(&self) -> MessageClass {
+        self.class
+    }
+
+    /// Returns the method of the message.
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Returns the length of the message excluding the header.
+    pub fn message_len(&self) -> u16 {
+        self.message_len
+    }
+
+    /// Returns the size of the whole message (i.e., `MessageHeader::SIZE + self.message_len()`).
+    pub fn message_size(&self) -> usize {
+        Self::SIZE + usize::from(self.message_len)
+    }
+
+    /// Returns the value of the magic cookie field.
+    pub fn magic_cookie(&self) -> u32 {
+        self.magic_cookie
+    }
+
+    /// Returns `true` if the magic cookie field has the fixed value `0x2112A442`.
+    pub fn has_magic_cookie(&self) -> bool {
+        self.magic_cookie == MAGIC_COOKIE
+    }
+
+    /// Returns the transaction ID of the message.
+    pub fn transaction_id(&self) -> TransactionId {
+        self.transaction_id
+    }
+}
+
 #[derive(Debug, Default)]
 struct MessageHeaderDecoder {
     message_type: U16beDecoder,
     message_len: U16beDecoder,
     magic_cookie: U32beDecoder,
     transaction_id: CopyableBytesDecoder<[u8; 12]>,
+    classic: bool,
 }
 impl MessageHeaderDecoder {
     fn check_magic_cookie(&self, magic_cookie: u32) -> Result<()> {
+        if self.classic {
+            return Ok(());
+        }
         track_assert_eq!(
             magic_cookie,
             MAGIC_COOKIE,
@@ -287,7 +494,7 @@ impl MessageHeaderDecoder {
     }
 }
 impl Decode for MessageHeaderDecoder {
-    type Item = (Type, u16, TransactionId);
+    type Item = (Type, u16, u32, TransactionId);
 
     fn decode(&mut self, buf: &[u8], eos: Eos) -> Result<usize> {
         let mut offset = 0;
@@ -305,7 +512,7 @@ impl Decode for MessageHeaderDecoder {
         let magic_cookie = track!(self.magic_cookie.finish_decoding())?;
         let transaction_id = TransactionId::new(track!(self.transaction_id.finish_decoding())?);
         track!(self.check_magic_cookie(magic_cookie); message_type, message_len, transaction_id)?;
-        Ok((message_type, message_len, transaction_id))
+        Ok((message_type, message_len, magic_cookie, transaction_id))
     }
 
     fn requiring_bytes(&self) -> ByteCount {
@@ -323,25 +530,62 @@ impl Decode for MessageHeaderDecoder {
 
 #[derive(Debug)]
 struct AttributesDecoder<A: Attribute> {
-    inner: Collect<LosslessAttributeDecoder<A>, Vec<LosslessAttribute<A>>>,
+    inner: LosslessAttributeDecoder<A>,
+    attributes: Vec<LosslessAttribute<A>>,
+    offsets: Vec<usize>,
+    attribute_offset: usize,
+    consumed_bytes: usize,
+    is_completed: bool,
     last_error: Option<Error>,
     is_eos: bool,
 }
+impl<A: Attribute> AttributesDecoder<A> {
+    fn decode_attributes(&mut self, buf: &[u8], eos: Eos) -> Result<usize> {
+        if self.is_completed {
+            return Ok(0);
+        }
+
+        let mut offset = 0;
+        while offset < buf.len() {
+            let size = track!(self.inner.decode(&buf[offset..], eos))?;
+            offset += size;
+            self.consumed_bytes += size;
+            if !self.inner.is_idle() {
+                return Ok(offset);
+            }
+
+            let attribute = track!(self.inner.finish_decoding())?;
+            self.attributes.push(attribute);
+            self.offsets.push(self.attribute_offset);
+            self.attribute_offset = self.consumed_bytes;
+        }
+        if eos.is_reached() {
+            self.is_completed = true;
+        }
+        Ok(offset)
+    }
+}
 impl<A: Attribute> Default for AttributesDecoder<A> {
     fn default() -> Self {
         AttributesDecoder {
             inner: Default::default(),
+            attributes: Vec::new(),
+            offsets: Vec::new(),
+            attribute_offset: MessageHeader::SIZE,
+            consumed_bytes: MessageHeader::SIZE,
+            is_completed: false,
             last_error: None,
             is_eos: false,
         }
     }
 }
 impl<A: Attribute> Decode for AttributesDecoder<A> {
-    type Item = Vec<LosslessAttribute<A>>;
+    /// The decoded attributes and their offsets from the head of the message.
+    type Item = (Vec<LosslessAttribute<A>>, Vec<usize>);
 
     fn decode(&mut self, buf: &[u8], eos: Eos) -> Result<usize> {
         if self.last_error.is_none() {
-            match track!(self.inner.decode(buf, eos)) {
+            match track!(self.decode_attributes(buf, eos)) {
                 Err(e) => {
                     self.last_error = Some(e);
                 }
@@ -356,15 +600,26 @@ impl<A: Attribute> Decode for AttributesDecoder<A> {
 
     fn finish_decoding(&mut self) -> Result<Self::Item> {
         self.is_eos = false;
+        self.attribute_offset = MessageHeader::SIZE;
+        self.consumed_bytes = MessageHeader::SIZE;
+        let attributes = mem::take(&mut self.attributes);
+        let offsets = mem::take(&mut self.offsets);
         if let Some(e) = self.last_error.take() {
+            self.is_completed = false;
             return Err(track!(e));
         }
-        track!(self.inner.finish_decoding())
+        track_assert!(self.is_completed, ErrorKind::IncompleteDecoding);
+        self.is_completed = false;
+        Ok((attributes, offsets))
     }
 
     fn requiring_bytes(&self) -> ByteCount {
         if self.last_error.is_none() {
-            self.inner.requiring_bytes()
+            if self.is_completed {
+                ByteCount::Finite(0)
+            } else {
+                self.inner.requiring_bytes()
+            }
         } else if self.is_eos {
             ByteCount::Finite(0)
         } else {
@@ -374,7 +629,7 @@ impl<A: Attribute> Decode for AttributesDecoder<A> {
 
     fn is_idle(&self) -> bool {
         if self.last_error.is_none() {
-            self.inner.is_idle()
+            self.is_completed
         } else {
             self.is_eos
         }
@@ -386,6 +641,7 @@ impl<A: Attribute> Decode for AttributesDecoder<A> {
 pub struct MessageDecoder<A: Attribute> {
     header: Peekable<MessageHeaderDecoder>,
     attributes: Length<AttributesDecoder<A>>,
+    fingerprint: FingerprintDigest,
 }
 impl<A: Attribute> MessageDecoder<A> {
     /// Makes a new `MessageDecoder` instance.
@@ -393,31 +649,60 @@ impl<A: Attribute> MessageDecoder<A> {
         Self::default()
     }
 
+    /// Makes a new `MessageDecoder` instance that also accepts [RFC 3489] classic STUN messages.
+    ///
+    /// The resulting decoder does not check the magic cookie field of messages,
+    /// and it is treated as the first 32 bits of the 128-bit transaction ID
+    /// (see [`Message::classic_transaction_id`]).
+    ///
+    /// [RFC 3489]: https://tools.ietf.org/html/rfc3489
+    pub fn new_classic() -> Self {
+        let mut this = Self::default();
+        this.header.inner_mut().classic = true;
+        this
+    }
+
     fn finish_decoding_with_header(
         &mut self,
         method: Method,
         class: MessageClass,
+        magic_cookie: u32,
         transaction_id: TransactionId,
     ) -> Result<Message<A>> {
-        let attributes = track!(self.attributes.finish_decoding())?;
+        let (attributes, offsets) = track!(self.attributes.finish_decoding())?;
         let mut message = Message {
             class,
             method,
+            magic_cookie,
             transaction_id,
             attributes,
+            computed_fingerprint: None,
+            attribute_offset: None,
         };
 
+        let fingerprint = mem::take(&mut self.fingerprint);
+        if message
+            .attributes
+            .last()
+            .is_some_and(|a| a.get_type().as_u16() == Fingerprint::CODEPOINT)
+        {
+            message.computed_fingerprint = Some(fingerprint.finalize());
+        }
+
         let attributes_len = message.attributes.len();
-        for i in 0..attributes_len {
+        for (i, &offset) in offsets.iter().enumerate() {
             unsafe {
                 let message_mut = &mut *(&mut message as *mut Message<A>);
                 let attr = message_mut.attributes.get_unchecked_mut(i);
                 message.attributes.set_len(i);
+                message.attribute_offset = Some(offset);
                 let decode_result = track!(attr.after_decode(&message));
                 message.attributes.set_len(attributes_len);
                 decode_result?;
             }
         }
+        message.computed_fingerprint = None;
+        message.attribute_offset = None;
         Ok(message)
     }
 }
@@ -426,6 +711,7 @@ impl<A: Attribute> Default for MessageDecoder<A> {
         MessageDecoder {
             header: Default::default(),
             attributes: Default::default(),
+            fingerprint: Default::default(),
         }
     }
 }
@@ -437,16 +723,34 @@ impl<A: Attribute> Decode for MessageDecoder<A> {
         if !self.header.is_idle() {
             bytecodec_try_decode!(self.header, offset, buf, eos);
 
-            let message_len = self.header.peek().expect("never fails").1;
+            let (message_type, message_len, magic_cookie, transaction_id) =
+                *self.header.peek().expect("never fails");
             track!(self.attributes.set_expected_bytes(u64::from(message_len)))?;
+
+            // The last 8 bytes are for a `FINGERPRINT` attribute (if any)
+            let mut fingerprint = FingerprintDigest::new(
+                MessageHeader::SIZE + usize::from(message_len).saturating_sub(8),
+            );
+            fingerprint.update(&header_bytes(
+                message_type,
+                message_len,
+                magic_cookie,
+                transaction_id,
+            ));
+            self.fingerprint = fingerprint;
+        }
+        if !self.attributes.is_idle() {
+            let size = track!(self.attributes.decode(&buf[offset..], eos))?;
+            self.fingerprint.update(&buf[offset..][..size]);
+            offset += size;
         }
-        bytecodec_try_decode!(self.attributes, offset, buf, eos);
         Ok(offset)
     }
 
     fn finish_decoding(&mut self) -> Result<Self::Item> {
-        let (Type { method, class }, _, transaction_id) = track!(self.header.finish_decoding())?;
-        match self.finish_decoding_with_header(method, class, transaction_id) {
+        let (Type { method, class }, _, magic_cookie, transaction_id) =
+            track!(self.header.finish_decoding())?;
+        match self.finish_decoding_with_header(method, class, magic_cookie, transaction_id) {
             Err(error) => Ok(Err(BrokenMessage {
                 method,
                 class,
@@ -469,75 +773,165 @@ impl<A: Attribute> Decode for MessageDecoder<A> {
 }
 
 /// [`Message`] encoder.
+///
+/// In addition to the [`Encode`] implementation, this provides methods that write messages directly into
+/// caller-provided buffers (e.g., [`MessageEncoder::encode_into_slice`]).
+/// They do not allocate any intermediate buffers, so the buffers can be reused across messages.
+///
+/// If the last attribute of a message is `FINGERPRINT`, its value is always overwritten by
+/// the CRC-32 value calculated from the encoded bytes preceding the attribute,
+/// whatever the value of the given [`Fingerprint`] is
+/// (e.g., an instance taken from a received message is re-calculated for the new message).
+/// To make a message that has an arbitrary (e.g., broken) `FINGERPRINT` value,
+/// modify the encoded bytes directly.
 #[derive(Debug)]
 pub struct MessageEncoder<A: Attribute> {
-    message_type: U16beEncoder,
-    message_len: U16beEncoder,
-    magic_cookie: U32beEncoder,
-    transaction_id: BytesEncoder<TransactionId>,
-    attributes: PreEncode<Repeat<LosslessAttributeEncoder<A>, vec::IntoIter<LosslessAttribute<A>>>>,
+    attribute: LosslessAttributeEncoder<A>,
+    bytes: BytesEncoder,
 }
 impl<A: Attribute> MessageEncoder<A> {
     /// Makes a new `MessageEncoder` instance.
     pub fn new() -> Self {
         Self::default()
     }
-}
-impl<A: Attribute> Default for MessageEncoder<A> {
-    fn default() -> Self {
-        MessageEncoder {
-            message_type: Default::default(),
-            message_len: Default::default(),
-            magic_cookie: Default::default(),
-            transaction_id: Default::default(),
-            attributes: Default::default(),
-        }
+
+    /// Encodes `message` into the head of `buf` and returns the number of written bytes.
+    ///
+    /// # Errors
+    ///
+    /// If `buf` is too short to hold the encoded message,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use stun_codec::rfc5389::{methods::BINDING, Attribute};
+    /// use stun_codec::{Message, MessageClass, MessageEncoder, TransactionId};
+    ///
+    /// let message = Message::<Attribute>::new(MessageClass::Request, BINDING, TransactionId::new([3; 12]));
+    ///
+    /// let mut buf = [0; 1500];
+    /// let size = MessageEncoder::new().encode_into_slice(message, &mut buf).unwrap();
+    /// assert_eq!(size, 20);
+    /// ```
+    pub fn encode_into_slice(&mut self, message: Message<A>, buf: &mut [u8]) -> Result<usize> {
+        let mut buf = SliceBuf { buf, len: 0 };
+        track!(self.encode_message(message, &mut buf))
     }
-}
-impl<A: Attribute> Encode for MessageEncoder<A> {
-    type Item = Message<A>;
 
-    fn encode(&mut self, buf: &mut [u8], eos: Eos) -> Result<usize> {
-        let mut offset = 0;
-        bytecodec_try_encode!(self.message_type, offset, buf, eos);
-        bytecodec_try_encode!(self.message_len, offset, buf, eos);
-        bytecodec_try_encode!(self.magic_cookie, offset, buf, eos);
-        bytecodec_try_encode!(self.transaction_id, offset, buf, eos);
-        bytecodec_try_encode!(self.attributes, offset, buf, eos);
-        Ok(offset)
+    /// Encodes `message` and appends the resulting bytes to `buf`.
+    ///
+    /// This returns the number of appended bytes.
+    pub fn encode_into_vec(&mut self, message: Message<A>, buf: &mut Vec<u8>) -> Result<usize> {
+        track!(self.encode_message(message, buf))
     }
 
-    fn start_encoding(&mut self, mut item: Self::Item) -> Result<()> {
-        let attributes_len = item.attributes.len();
+    /// Encodes `message` and appends the resulting bytes to `buf`.
+    ///
+    /// This returns the number of appended bytes.
+    ///
+    /// This method is available only if the `tokio` feature is enabled.
+    #[cfg(feature = "tokio")]
+    pub fn encode_into_bytes_mut(
+        &mut self,
+        message: Message<A>,
+        buf: &mut bytes::BytesMut,
+    ) -> Result<usize> {
+        track!(self.encode_message(message, buf))
+    }
+
+    fn encode_message<B: EncodeBuf>(&mut self, message: Message<A>, buf: &mut B) -> Result<usize> {
+        let start = buf.len();
+        let result = track!(self.encode_message_into(message, buf, start));
+        if result.is_err() {
+            buf.truncate(start);
+            self.attribute = Default::default();
+        }
+        result
+    }
+
+    fn encode_message_into<B: EncodeBuf>(
+        &mut self,
+        mut message: Message<A>,
+        buf: &mut B,
+        start: usize,
+    ) -> Result<usize> {
+        let attributes_len = message.attributes.len();
         for i in 0..attributes_len {
             unsafe {
-                let item_mut = &mut *(&mut item as *mut Message<A>);
-                let attr = item_mut.attributes.get_unchecked_mut(i);
-                item.attributes.set_len(i);
-                let encode_result = track!(attr.before_encode(&item));
-                item.attributes.set_len(attributes_len);
+                let message_mut = &mut *(&mut message as *mut Message<A>);
+                let attr = message_mut.attributes.get_unchecked_mut(i);
+                message.attributes.set_len(i);
+                let encode_result = track!(attr.before_encode(&message));
+                message.attributes.set_len(attributes_len);
                 encode_result?;
             }
         }
 
         let message_type = Type {
-            class: item.class,
-            method: item.method,
+            class: message.class,
+            method: message.method,
         };
-        track!(self.message_type.start_encoding(message_type.as_u16()))?;
-        track!(self.magic_cookie.start_encoding(MAGIC_COOKIE))?;
-        track!(self.transaction_id.start_encoding(item.transaction_id))?;
-        track!(self.attributes.start_encoding(item.attributes.into_iter()))?;
+        let has_fingerprint = message
+            .attributes
+            .last()
+            .is_some_and(|a| a.get_type().as_u16() == Fingerprint::CODEPOINT);
+
+        track!(buf.extend_zeroed(MessageHeader::SIZE))?;
+        for attr in message.attributes {
+            track!(self.attribute.start_encoding(attr))?;
+            let size = self.attribute.exact_requiring_bytes() as usize;
+            let dst = track!(buf.extend_zeroed(size))?;
+            track!(self.attribute.encode(dst, Eos::new(true)))?;
+            track_assert!(self.attribute.is_idle(), ErrorKind::InconsistentState);
+        }
 
-        let message_len = self.attributes.exact_requiring_bytes();
+        let bytes = &mut buf.as_mut_slice()[start..];
+        let message_len = bytes.len() - MessageHeader::SIZE;
         track_assert!(
             message_len < 0x10000,
             ErrorKind::InvalidInput,
             "Too large message length: actual={}, limit=0xFFFF",
             message_len
         );
-        track!(self.message_len.start_encoding(message_len as u16))?;
-        Ok(())
+        bytes[..MessageHeader::SIZE].copy_from_slice(&header_bytes(
+            message_type,
+            message_len as u16,
+            message.magic_cookie,
+            message.transaction_id,
+        ));
+
+        if has_fingerprint && message_len >= 8 {
+            // Calculates the value of the `FINGERPRINT` attribute (i.e., the last 4 bytes)
+            // from the bytes preceding the attribute
+            let mut fingerprint = FingerprintDigest::new(bytes.len() - 8);
+            fingerprint.update(bytes);
+            let offset = bytes.len() - 4;
+            BigEndian::write_u32(&mut bytes[offset..], fingerprint.finalize());
+        }
+        Ok(bytes.len())
+    }
+}
+impl<A: Attribute> Default for MessageEncoder<A> {
+    fn default() -> Self {
+        MessageEncoder {
+            attribute: Default::default(),
+            bytes: Default::default(),
+        }
+    }
+}
+impl<A: Attribute> Encode for MessageEncoder<A> {
+    type Item = Message<A>;
+
+    fn encode(&mut self, buf: &mut [u8], eos: Eos) -> Result<usize> {
+        track!(self.bytes.encode(buf, eos))
+    }
+
+    fn start_encoding(&mut self, item: Self::Item) -> Result<()> {
+        track_assert!(self.bytes.is_idle(), ErrorKind::EncoderFull);
+        let mut bytes = Vec::new();
+        track!(self.encode_into_vec(item, &mut bytes))?;
+        track!(self.bytes.start_encoding(bytes))
     }
 
     fn requiring_bytes(&self) -> ByteCount {
@@ -545,26 +939,118 @@ impl<A: Attribute> Encode for MessageEncoder<A> {
     }
 
     fn is_idle(&self) -> bool {
-        self.transaction_id.is_idle() && self.attributes.is_idle()
+        self.bytes.is_idle()
     }
 }
 impl<A: Attribute> SizedEncode for MessageEncoder<A> {
     fn exact_requiring_bytes(&self) -> u64 {
-        self.message_type.exact_requiring_bytes()
-            + self.message_len.exact_requiring_bytes()
-            + self.magic_cookie.exact_requiring_bytes()
-            + self.transaction_id.exact_requiring_bytes()
-            + self.attributes.exact_requiring_bytes()
+        self.bytes.exact_requiring_bytes()
+    }
+}
+
+/// Destination buffer of [`MessageEncoder`].
+trait EncodeBuf {
+    fn len(&self) -> usize;
+
+    /// Appends `n` zero bytes and returns the appended part.
+    fn extend_zeroed(&mut self, n: usize) -> Result<&mut [u8]>;
+
+    fn truncate(&mut self, len: usize);
+
+    fn as_mut_slice(&mut self) -> &mut [u8];
+}
+
+struct SliceBuf<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+impl EncodeBuf for SliceBuf<'_> {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn extend_zeroed(&mut self, n: usize) -> Result<&mut [u8]> {
+        let start = self.len;
+        track_assert!(
+            n <= self.buf.len() - start,
+            ErrorKind::InvalidInput,
+            "Too short buffer: length={}, required={}",
+            self.buf.len(),
+            start + n
+        );
+        self.len += n;
+        Ok(&mut self.buf[start..self.len])
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.len = cmp::min(self.len, len);
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.buf[..self.len]
     }
 }
+impl EncodeBuf for Vec<u8> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn extend_zeroed(&mut self, n: usize) -> Result<&mut [u8]> {
+        let start = Vec::len(self);
+        self.resize(start + n, 0);
+        Ok(&mut self[start..])
+    }
+
+    fn truncate(&mut self, len: usize) {
+        Vec::truncate(self, len);
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        self
+    }
+}
+#[cfg(feature = "tokio")]
+impl EncodeBuf for bytes::BytesMut {
+    fn len(&self) -> usize {
+        bytes::BytesMut::len(self)
+    }
+
+    fn extend_zeroed(&mut self, n: usize) -> Result<&mut [u8]> {
+        let start = bytes::BytesMut::len(self);
+        self.resize(start + n, 0);
+        Ok(&mut self[start..])
+    }
+
+    fn truncate(&mut self, len: usize) {
+        bytes::BytesMut::truncate(self, len);
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        self
+    }
+}
+
+fn header_bytes(
+    message_type: Type,
+    message_len: u16,
+    magic_cookie: u32,
+    transaction_id: TransactionId,
+) -> [u8; MessageHeader::SIZE] {
+    let mut bytes = [0; MessageHeader::SIZE];
+    BigEndian::write_u16(&mut bytes[0..2], message_type.as_u16());
+    BigEndian::write_u16(&mut bytes[2..4], message_len);
+    BigEndian::write_u32(&mut bytes[4..8], magic_cookie);
+    bytes[8..].copy_from_slice(transaction_id.as_bytes());
+    bytes
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Type {
-    class: MessageClass,
-    method: Method,
+pub(crate) struct Type {
+    pub(crate) class: MessageClass,
+    pub(crate) method: Method,
 }
 impl Type {
-    fn as_u16(self) -> u16 {
+    pub(crate) fn as_u16(self) -> u16 {
         let class = self.class as u16;
         let method = self.method.as_u16();
         (method & 0b0000_0000_1111)
@@ -574,7 +1060,7 @@ impl Type {
             | ((method & 0b1111_1000_0000) << 9)
     }
 
-    fn from_u16(value: u16) -> Result<Self> {
+    pub(crate) fn from_u16(value: u16) -> Result<Self> {
         track_assert!(
             value >> 14 == 0,
             ErrorKind::InvalidInput,
@@ -593,10 +1079,11 @@ impl Type {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::rfc5389::attributes::MappedAddress;
+    use crate::rfc5389::attributes::{MappedAddress, Software};
     use crate::rfc5389::methods::BINDING;
+    use crate::rfc5389::Attribute as Rfc5389Attribute;
     use crate::{MessageClass, TransactionId};
-    use bytecodec::DecodeExt;
+    use bytecodec::{DecodeExt, EncodeExt};
     use trackable::result::TestResult;
 
     #[test]
@@ -620,4 +1107,135 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn message_header_works() -> TestResult {
+        let bytes = [
+            0, 1, 0, 12, 33, 18, 164, 66, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 0, 1, 0, 8, 0, 1, 0,
+            80, 127, 0, 0, 1,
+        ];
+        let header = track!(MessageHeader::peek(&bytes))?;
+        assert_eq!(header.class(), MessageClass::Request);
+        assert_eq!(header.method(), BINDING);
+        assert_eq!(header.message_len(), 12);
+        assert_eq!(header.message_size(), bytes.len());
+        assert!(header.has_magic_cookie());
+        assert_eq!(header.transaction_id(), TransactionId::new([3; 12]));
+        assert!(MessageHeader::looks_like_stun(&bytes));
+
+        // Truncated
+        assert!(MessageHeader::peek(&bytes[..19]).is_err());
+        assert!(MessageHeader::peek(&bytes[..20]).is_ok());
+        assert!(!MessageHeader::looks_like_stun(&bytes[..20]));
+
+        // Not STUN
+        let mut other = bytes;
+        other[0] = 0x80;
+        assert!(MessageHeader::peek(&other).is_err());
+        assert!(!MessageHeader::looks_like_stun(&other));
+
+        // Classic STUN
+        let mut classic = bytes;
+        classic[4] = 0;
+        let header = track!(MessageHeader::peek(&classic))?;
+        assert!(!header.has_magic_cookie());
+        assert!(!MessageHeader::looks_like_stun(&classic));
+        Ok(())
+    }
+
+    #[test]
+    fn fingerprint_is_calculated_while_encoding() -> TestResult {
+        let mut message = Message::<Rfc5389Attribute>::new(
+            MessageClass::Request,
+            BINDING,
+            TransactionId::new([3; 12]),
+        );
+        message.add_attribute(track!(Software::new("foo".to_owned()))?);
+        message.add_attribute(Fingerprint::placeholder());
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+
+        let crc32 = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+        let expected = crc32.checksum(&bytes[..bytes.len() - 8]) ^ 0x5354_554e;
+        assert_eq!(bytes[bytes.len() - 4..], expected.to_be_bytes());
+
+        let decoded = track!(MessageDecoder::<Rfc5389Attribute>::new().decode_from_bytes(&bytes))?;
+        let decoded = decoded.expect("never fails");
+        assert_eq!(
+            decoded.get_attribute::<Fingerprint>().map(|a| a.crc32()),
+            Some(expected)
+        );
+
+        // Corrupt fingerprint
+        let mut broken = bytes.clone();
+        broken[20] ^= 1;
+        let decoded = track!(MessageDecoder::<Rfc5389Attribute>::new().decode_from_bytes(&broken))?;
+        assert!(decoded.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn encoder_overwrites_non_placeholder_fingerprint() -> TestResult {
+        let mut message = Message::<Rfc5389Attribute>::new(
+            MessageClass::Request,
+            BINDING,
+            TransactionId::new([3; 12]),
+        );
+        message.add_attribute(track!(Software::new("foo".to_owned()))?);
+        message.add_attribute(Fingerprint::placeholder());
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+        let decoded = track!(MessageDecoder::<Rfc5389Attribute>::new().decode_from_bytes(&bytes))?;
+        let relayed = decoded
+            .expect("never fails")
+            .get_attribute::<Fingerprint>()
+            .cloned()
+            .expect("never fails");
+        assert_ne!(relayed.crc32(), 0);
+
+        // The value of `relayed` is valid only for the original message
+        let mut message = Message::<Rfc5389Attribute>::new(
+            MessageClass::Request,
+            BINDING,
+            TransactionId::new([4; 12]),
+        );
+        message.add_attribute(track!(Software::new("foo".to_owned()))?);
+        message.add_attribute(relayed.clone());
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+        assert_ne!(bytes[bytes.len() - 4..], relayed.crc32().to_be_bytes());
+
+        let decoded = track!(MessageDecoder::<Rfc5389Attribute>::new().decode_from_bytes(&bytes))?;
+        assert!(decoded.is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn encoder_writes_into_caller_provided_buffers() -> TestResult {
+        let mut message = Message::<Rfc5389Attribute>::new(
+            MessageClass::Request,
+            BINDING,
+            TransactionId::new([3; 12]),
+        );
+        message.add_attribute(track!(Software::new("foo".to_owned()))?);
+        message.add_attribute(Fingerprint::placeholder());
+        let expected = track!(MessageEncoder::new().encode_into_bytes(message.clone()))?;
+
+        let mut encoder = MessageEncoder::new();
+        let mut buf = [0; 64];
+        let size = track!(encoder.encode_into_slice(message.clone(), &mut buf))?;
+        assert_eq!(&buf[..size], &expected[..]);
+
+        // Too short buffer
+        assert!(encoder
+            .encode_into_slice(message.clone(), &mut buf[..size - 1])
+            .is_err());
+
+        // Appends to the existing bytes
+        let mut buf = vec![1, 2, 3];
+        let size = track!(encoder.encode_into_vec(message.clone(), &mut buf))?;
+        assert_eq!(size, expected.len());
+        assert_eq!(&buf[..3], [1, 2, 3]);
+        assert_eq!(&buf[3..], &expected[..]);
+        let size = track!(encoder.encode_into_vec(message, &mut buf))?;
+        assert_eq!(buf.len(), 3 + size * 2);
+        Ok(())
+    }
 }
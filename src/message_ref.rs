@@ -0,0 +1,384 @@
+use crate::attribute::{padded_len, Attribute, AttributeType, RawAttribute, ATTRIBUTE_HEADER_LEN};
+use crate::message::{Message, MessageClass, MessageHeader};
+use crate::rfc5389::attributes::{Fingerprint, FingerprintDigest};
+use crate::{Method, TransactionId};
+use bytecodec::{DecodeExt, ErrorKind, Result, TryTaggedDecode};
+
+/// Borrowed view of an encoded STUN message.
+///
+/// Unlike [`MessageDecoder`], this does not allocate any memory.
+/// [`MessageRef::new`] only validates the header and the boundaries of the attributes,
+/// and each attribute is decoded lazily when it is requested via [`MessageRef::get_attribute`].
+///
+/// Note that [`Attribute::after_decode`] of a lazily decoded attribute is called with
+/// a message that has the same header as this view but no attributes.
+/// So attributes that depend on the values of the preceding attributes cannot be
+/// verified through the resulting instances.
+/// `FINGERPRINT` is verified against the bytes of this view preceding the attribute
+/// (it is rejected if it is not the last attribute), and
+/// `MESSAGE-INTEGRITY` can be checked by passing [`MessageRef::as_bytes`] to its `check_*` methods.
+///
+/// [`MessageDecoder`]: crate::MessageDecoder
+///
+/// # Examples
+///
+/// ```
+/// use bytecodec::EncodeExt;
+/// use stun_codec::rfc5389::{attributes::Software, methods::BINDING, Attribute};
+/// use stun_codec::{Message, MessageClass, MessageEncoder, MessageRef, TransactionId};
+///
+/// let mut message = Message::<Attribute>::new(MessageClass::Request, BINDING, TransactionId::new([3; 12]));
+/// message.add_attribute(Attribute::Software(Software::new("foo".to_owned()).unwrap()));
+/// let bytes = MessageEncoder::new().encode_into_bytes(message).unwrap();
+///
+/// let message = MessageRef::new(&bytes).unwrap();
+/// assert_eq!(message.method(), BINDING);
+/// assert_eq!(message.raw_attributes().count(), 1);
+///
+/// let software = message.get_attribute::<Software>().unwrap().unwrap();
+/// assert_eq!(software.description(), "foo");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct MessageRef<'a> {
+    bytes: &'a [u8],
+    header: MessageHeader,
+}
+impl<'a> MessageRef<'a> {
+    /// Makes a new `MessageRef` instance that refers to `bytes`.
+    ///
+    /// # Errors
+    ///
+    /// If `bytes` is not a well-formed STUN message (e.g., it has an invalid header or
+    /// the length of an attribute exceeds the end of the message),
+    /// this will return an `ErrorKind::InvalidInput` error.
+    pub fn new(bytes: &'a [u8]) -> Result<Self> {
+        let header = track!(MessageHeader::peek(bytes))?;
+        track_assert!(header.has_magic_cookie(), ErrorKind::InvalidInput; header.magic_cookie());
+        track_assert_eq!(header.message_size(), bytes.len(), ErrorKind::InvalidInput);
+
+        let mut offset = MessageHeader::SIZE;
+        while offset < bytes.len() {
+            track_assert!(
+                offset + ATTRIBUTE_HEADER_LEN <= bytes.len(),
+                ErrorKind::InvalidInput; offset
+            );
+            let value_len = usize::from(u16::from_be_bytes([bytes[offset + 2], bytes[offset + 3]]));
+            offset += ATTRIBUTE_HEADER_LEN + padded_len(value_len);
+            track_assert!(offset <= bytes.len(), ErrorKind::InvalidInput; offset, bytes.len());
+        }
+
+        Ok(MessageRef { bytes, header })
+    }
+
+    /// Returns the class of the message.
+    pub fn class(&self) -> MessageClass {
+        self.header.class()
+    }
+
+    /// Returns the method of the message.
+    pub fn method(&self) -> Method {
+        self.header.method()
+    }
+
+    /// Returns the transaction ID of the message.
+    pub fn transaction_id(&self) -> TransactionId {
+        self.header.transaction_id()
+    }
+
+    /// Returns the header of the message.
+    pub fn header(&self) -> MessageHeader {
+        self.header
+    }
+
+    /// Returns the bytes of the whole message.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Returns an iterator that iterates over the attributes in the message without decoding their values.
+    pub fn raw_attributes(&self) -> RawAttributesRef<'a> {
+        RawAttributesRef {
+            bytes: self.bytes,
+            offset: MessageHeader::SIZE,
+        }
+    }
+
+    /// Returns the first attribute that has the type `attr_type`.
+    pub fn get_raw_attribute(&self, attr_type: AttributeType) -> Option<RawAttributeRef<'a>> {
+        self.raw_attributes().find(|a| a.get_type() == attr_type)
+    }
+
+    /// Decodes the first attribute that can be decoded as `T`.
+    ///
+    /// If there is no such attribute, this will return `None`.
+    pub fn get_attribute<T: Attribute>(&self) -> Option<Result<T>> {
+        let mut decoder = T::Decoder::default();
+        for attr in self.raw_attributes() {
+            match track!(decoder.try_start_decoding(attr.get_type())) {
+                Err(e) => return Some(Err(e)),
+                Ok(false) => {}
+                Ok(true) => return Some(track!(self.finish_decoding_attribute(decoder, attr))),
+            }
+        }
+        None
+    }
+
+    /// Decodes `attr` as `T`.
+    ///
+    /// If `T` does not support the type of `attr`, this will return `None`.
+    pub fn decode_attribute<T: Attribute>(&self, attr: RawAttributeRef<'a>) -> Option<Result<T>> {
+        let mut decoder = T::Decoder::default();
+        match track!(decoder.try_start_decoding(attr.get_type())) {
+            Err(e) => Some(Err(e)),
+            Ok(false) => None,
+            Ok(true) => Some(track!(self.finish_decoding_attribute(decoder, attr))),
+        }
+    }
+
+    fn finish_decoding_attribute<T: Attribute>(
+        &self,
+        mut decoder: T::Decoder,
+        attr: RawAttributeRef<'a>,
+    ) -> Result<T> {
+        let mut item = track!(decoder.decode_from_bytes(attr.value()))?;
+        let mut header_only =
+            Message::<RawAttribute>::new(self.class(), self.method(), self.transaction_id());
+        header_only.set_attribute_offset(Some(attr.offset()));
+        if attr.get_type().as_u16() == Fingerprint::CODEPOINT
+            && attr.end_offset() == self.bytes.len()
+        {
+            let mut fingerprint = FingerprintDigest::new(attr.offset());
+            fingerprint.update(self.bytes);
+            header_only.set_computed_fingerprint(Some(fingerprint.finalize()));
+        }
+        track!(item.after_decode(&header_only))?;
+        Ok(item)
+    }
+}
+
+/// Borrowed view of an attribute in an encoded STUN message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RawAttributeRef<'a> {
+    attr_type: AttributeType,
+    value: &'a [u8],
+    offset: usize,
+}
+impl<'a> RawAttributeRef<'a> {
+    /// Returns the type of the attribute.
+    pub fn get_type(&self) -> AttributeType {
+        self.attr_type
+    }
+
+    /// Returns the value bytes of the attribute (excluding the padding).
+    pub fn value(&self) -> &'a [u8] {
+        self.value
+    }
+
+    /// Returns the offset of the attribute from the head of the message.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the offset of the end of the attribute (including the padding) from the head of the message.
+    pub fn end_offset(&self) -> usize {
+        self.offset + ATTRIBUTE_HEADER_LEN + padded_len(self.value.len())
+    }
+}
+
+/// An iterator over the attributes in a [`MessageRef`].
+#[derive(Debug, Clone)]
+pub struct RawAttributesRef<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+impl<'a> Iterator for RawAttributesRef<'a> {
+    type Item = RawAttributeRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.bytes.len() {
+            return None;
+        }
+        let offset = self.offset;
+        let bytes = &self.bytes[offset..];
+        let attr_type = AttributeType::new(u16::from_be_bytes([bytes[0], bytes[1]]));
+        let value_len = usize::from(u16::from_be_bytes([bytes[2], bytes[3]]));
+        let value = &bytes[ATTRIBUTE_HEADER_LEN..][..value_len];
+        self.offset += ATTRIBUTE_HEADER_LEN + padded_len(value_len);
+        Some(RawAttributeRef {
+            attr_type,
+            value,
+            offset,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rfc5389::attributes::{MessageIntegrity, Software, XorMappedAddress};
+    use crate::rfc5389::methods::BINDING;
+    use crate::rfc5389::Attribute as Rfc5389Attribute;
+    use crate::MessageEncoder;
+    use bytecodec::EncodeExt;
+    use trackable::result::TestResult;
+
+    #[test]
+    fn message_ref_works() -> TestResult {
+        let addr = "192.0.2.1:32853".parse().unwrap();
+        let mut message = Message::<Rfc5389Attribute>::new(
+            MessageClass::SuccessResponse,
+            BINDING,
+            TransactionId::new([5; 12]),
+        );
+        message.add_attribute(track!(Software::new("foo".to_owned()))?);
+        message.add_attribute(XorMappedAddress::new(addr));
+        message.add_attribute(Fingerprint::placeholder());
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+
+        let message = track!(MessageRef::new(&bytes))?;
+        assert_eq!(message.class(), MessageClass::SuccessResponse);
+        assert_eq!(message.method(), BINDING);
+        assert_eq!(message.transaction_id(), TransactionId::new([5; 12]));
+
+        let attrs = message.raw_attributes().collect::<Vec<_>>();
+        assert_eq!(attrs.len(), 3);
+        assert_eq!(attrs[0].get_type(), AttributeType::new(Software::CODEPOINT));
+        assert_eq!(attrs[0].value(), b"foo");
+        assert_eq!(attrs[0].offset(), 20);
+        assert_eq!(attrs[0].end_offset(), 28);
+        assert_eq!(attrs[2].end_offset(), bytes.len());
+
+        let software = track!(message.get_attribute::<Software>().expect("never fails"))?;
+        assert_eq!(software.description(), "foo");
+        let xor_mapped = track!(message
+            .get_attribute::<XorMappedAddress>()
+            .expect("never fails"))?;
+        assert_eq!(xor_mapped.address(), addr);
+        let attr = track!(message
+            .decode_attribute::<Rfc5389Attribute>(attrs[0])
+            .expect("never fails"))?;
+        assert!(matches!(attr, Rfc5389Attribute::Software(_)));
+        assert!(message
+            .get_raw_attribute(AttributeType::new(0x7FFF))
+            .is_none());
+        assert!(message
+            .get_attribute::<Fingerprint>()
+            .expect("never fails")
+            .is_ok());
+
+        // Corrupt fingerprint
+        let mut broken = bytes.clone();
+        broken[21] ^= 1;
+        let message = track!(MessageRef::new(&broken))?;
+        assert!(message
+            .get_attribute::<Fingerprint>()
+            .expect("never fails")
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn fingerprint_that_is_not_last_is_rejected() -> TestResult {
+        let mut message = Message::<Rfc5389Attribute>::new(
+            MessageClass::Request,
+            BINDING,
+            TransactionId::new([5; 12]),
+        );
+        message.add_attribute(track!(Software::new("foo".to_owned()))?);
+        message.add_attribute(Fingerprint::placeholder());
+        let mut bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+
+        // Appends a `SOFTWARE` attribute after `FINGERPRINT`
+        bytes.extend_from_slice(&[0x80, 0x22, 0, 4, b'b', b'a', b'r', b'!']);
+        bytes[3] += 8;
+        let message = track!(MessageRef::new(&bytes))?;
+        let attr = message
+            .get_raw_attribute(AttributeType::new(Fingerprint::CODEPOINT))
+            .expect("never fails");
+        assert_ne!(attr.end_offset(), bytes.len());
+
+        // Even if the value is valid for the bytes preceding the attribute
+        let mut fingerprint = FingerprintDigest::new(attr.offset());
+        fingerprint.update(&bytes);
+        let crc32 = fingerprint.finalize().to_be_bytes();
+        let offset = attr.offset() + ATTRIBUTE_HEADER_LEN;
+        bytes[offset..offset + 4].copy_from_slice(&crc32);
+        let message = track!(MessageRef::new(&bytes))?;
+        assert!(message
+            .get_attribute::<Fingerprint>()
+            .expect("never fails")
+            .is_err());
+
+        // Same as `MessageDecoder`
+        let decoded =
+            track!(crate::MessageDecoder::<Rfc5389Attribute>::new().decode_from_bytes(&bytes))?;
+        assert!(decoded.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn message_integrity_can_be_checked_via_message_ref() -> TestResult {
+        let mut message = Message::<Rfc5389Attribute>::new(
+            MessageClass::Request,
+            BINDING,
+            TransactionId::new([5; 12]),
+        );
+        message.add_attribute(track!(Software::new("foo".to_owned()))?);
+        message.add_attribute(track!(MessageIntegrity::new_short_term_credential(
+            &message, "pass"
+        ))?);
+        message.add_attribute(Fingerprint::placeholder());
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+
+        let message = track!(MessageRef::new(&bytes))?;
+        let mi = track!(message
+            .get_attribute::<MessageIntegrity>()
+            .expect("never fails"))?;
+        assert!(mi
+            .check_short_term_credential(message.as_bytes(), "pass")
+            .is_ok());
+        assert!(mi
+            .check_short_term_credential(message.as_bytes(), "word")
+            .is_err());
+
+        // Tampered bytes
+        let mut broken = bytes.clone();
+        broken[24] = b'F';
+        assert!(mi.check_short_term_credential(&broken, "pass").is_err());
+
+        // Bytes that do not contain the attribute
+        assert!(mi
+            .check_short_term_credential(&bytes[..28], "pass")
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_message_ref_is_rejected() -> TestResult {
+        let mut message = Message::<Rfc5389Attribute>::new(
+            MessageClass::Request,
+            BINDING,
+            TransactionId::new([5; 12]),
+        );
+        message.add_attribute(track!(Software::new("foo".to_owned()))?);
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+        assert!(MessageRef::new(&bytes).is_ok());
+
+        // Too short
+        assert!(MessageRef::new(&bytes[..19]).is_err());
+
+        // Length mismatch
+        assert!(MessageRef::new(&bytes[..bytes.len() - 4]).is_err());
+
+        // Invalid magic cookie
+        let mut broken = bytes.clone();
+        broken[4] = 0;
+        assert!(MessageRef::new(&broken).is_err());
+
+        // Attribute exceeds the end of the message
+        let mut broken = bytes.clone();
+        broken[23] = 8;
+        assert!(MessageRef::new(&broken).is_err());
+        Ok(())
+    }
+}
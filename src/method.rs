@@ -1,4 +1,4 @@
-use crate::{rfc5389, rfc5766};
+use crate::{rfc5389, rfc5766, rfc6062};
 use bytecodec::{ErrorKind, Result};
 use std::fmt;
 
@@ -45,6 +45,9 @@ impl fmt::Display for Method {
             rfc5766::methods::CREATE_PERMISSION => write!(f, "create permission"),
             rfc5766::methods::DATA => write!(f, "data"),
             rfc5766::methods::SEND => write!(f, "send"),
+            rfc6062::methods::CONNECT => write!(f, "connect"),
+            rfc6062::methods::CONNECTION_BIND => write!(f, "connection bind"),
+            rfc6062::methods::CONNECTION_ATTEMPT => write!(f, "connection attempt"),
             Method(code) => write!(f, "unknown ({code})"),
         }
     }
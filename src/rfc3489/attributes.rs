@@ -0,0 +1,492 @@
+//! Attributes that are defined in [RFC 3489].
+//!
+//! Note that these attributes have been deprecated by [RFC 5389].
+//! They are only useful for communicating with classic STUN implementations.
+//!
+//! [RFC 3489]: https://tools.ietf.org/html/rfc3489
+//! [RFC 5389]: https://tools.ietf.org/html/rfc5389
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::net::SocketAddr;
+
+use bytecodec::bytes::{BytesEncoder, RemainingBytesDecoder};
+use bytecodec::{ByteCount, Decode, Encode, Eos, ErrorKind, Result, SizedEncode, TryTaggedDecode};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroizing;
+
+use crate::attribute::{Attribute, AttributeType};
+use crate::net::{SocketAddrDecoder, SocketAddrEncoder};
+
+macro_rules! impl_decode {
+    ($decoder:ty, $item:ident, $and_then:expr) => {
+        impl Decode for $decoder {
+            type Item = $item;
+
+            fn decode(&mut self, buf: &[u8], eos: Eos) -> Result<usize> {
+                track!(self.0.decode(buf, eos))
+            }
+
+            fn finish_decoding(&mut self) -> Result<Self::Item> {
+                track!(self.0.finish_decoding()).and_then($and_then)
+            }
+
+            fn requiring_bytes(&self) -> ByteCount {
+                self.0.requiring_bytes()
+            }
+
+            fn is_idle(&self) -> bool {
+                self.0.is_idle()
+            }
+        }
+        impl TryTaggedDecode for $decoder {
+            type Tag = AttributeType;
+
+            fn try_start_decoding(&mut self, attr_type: Self::Tag) -> Result<bool> {
+                Ok(attr_type.as_u16() == $item::CODEPOINT)
+            }
+        }
+    };
+}
+
+macro_rules! impl_encode {
+    ($encoder:ty, $item:ty, $map_from:expr) => {
+        impl Encode for $encoder {
+            type Item = $item;
+
+            fn encode(&mut self, buf: &mut [u8], eos: Eos) -> Result<usize> {
+                track!(self.0.encode(buf, eos))
+            }
+
+            #[allow(clippy::redundant_closure_call)]
+            fn start_encoding(&mut self, item: Self::Item) -> Result<()> {
+                track!(self.0.start_encoding($map_from(item).into()))
+            }
+
+            fn requiring_bytes(&self) -> ByteCount {
+                self.0.requiring_bytes()
+            }
+
+            fn is_idle(&self) -> bool {
+                self.0.is_idle()
+            }
+        }
+        impl SizedEncode for $encoder {
+            fn exact_requiring_bytes(&self) -> u64 {
+                self.0.exact_requiring_bytes()
+            }
+        }
+    };
+}
+
+/// `RESPONSE-ADDRESS` attribute.
+///
+/// See [RFC 3489 -- 11.2.2. RESPONSE-ADDRESS] about this attribute.
+///
+/// [RFC 3489 -- 11.2.2. RESPONSE-ADDRESS]: https://tools.ietf.org/html/rfc3489#section-11.2.2
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ResponseAddress(SocketAddr);
+
+impl ResponseAddress {
+    /// The codepoint of the type of the attribute.
+    pub const CODEPOINT: u16 = 0x0002;
+
+    /// Makes a new `ResponseAddress` instance.
+    pub fn new(addr: SocketAddr) -> Self {
+        ResponseAddress(addr)
+    }
+
+    /// Returns the address of this instance.
+    pub fn address(&self) -> SocketAddr {
+        self.0
+    }
+}
+
+impl Attribute for ResponseAddress {
+    type Decoder = ResponseAddressDecoder;
+    type Encoder = ResponseAddressEncoder;
+
+    fn get_type(&self) -> AttributeType {
+        AttributeType::new(Self::CODEPOINT)
+    }
+}
+
+/// [`ResponseAddress`] decoder.
+#[derive(Debug, Default)]
+pub struct ResponseAddressDecoder(SocketAddrDecoder);
+
+impl ResponseAddressDecoder {
+    /// Makes a new `ResponseAddressDecoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_decode!(ResponseAddressDecoder, ResponseAddress, |item| Ok(
+    ResponseAddress(item)
+));
+
+/// [`ResponseAddress`] encoder.
+#[derive(Debug, Default)]
+pub struct ResponseAddressEncoder(SocketAddrEncoder);
+
+impl ResponseAddressEncoder {
+    /// Makes a new `ResponseAddressEncoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_encode!(
+    ResponseAddressEncoder,
+    ResponseAddress,
+    |item: Self::Item| item.0
+);
+
+/// `SOURCE-ADDRESS` attribute.
+///
+/// See [RFC 3489 -- 11.2.5. SOURCE-ADDRESS] about this attribute.
+///
+/// [RFC 3489 -- 11.2.5. SOURCE-ADDRESS]: https://tools.ietf.org/html/rfc3489#section-11.2.5
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SourceAddress(SocketAddr);
+
+impl SourceAddress {
+    /// The codepoint of the type of the attribute.
+    pub const CODEPOINT: u16 = 0x0004;
+
+    /// Makes a new `SourceAddress` instance.
+    pub fn new(addr: SocketAddr) -> Self {
+        SourceAddress(addr)
+    }
+
+    /// Returns the address of this instance.
+    pub fn address(&self) -> SocketAddr {
+        self.0
+    }
+}
+
+impl Attribute for SourceAddress {
+    type Decoder = SourceAddressDecoder;
+    type Encoder = SourceAddressEncoder;
+
+    fn get_type(&self) -> AttributeType {
+        AttributeType::new(Self::CODEPOINT)
+    }
+}
+
+/// [`SourceAddress`] decoder.
+#[derive(Debug, Default)]
+pub struct SourceAddressDecoder(SocketAddrDecoder);
+
+impl SourceAddressDecoder {
+    /// Makes a new `SourceAddressDecoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_decode!(SourceAddressDecoder, SourceAddress, |item| Ok(
+    SourceAddress(item)
+));
+
+/// [`SourceAddress`] encoder.
+#[derive(Debug, Default)]
+pub struct SourceAddressEncoder(SocketAddrEncoder);
+
+impl SourceAddressEncoder {
+    /// Makes a new `SourceAddressEncoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_encode!(SourceAddressEncoder, SourceAddress, |item: Self::Item| item
+    .0);
+
+/// `CHANGED-ADDRESS` attribute.
+///
+/// See [RFC 3489 -- 11.2.3. CHANGED-ADDRESS] about this attribute.
+///
+/// [RFC 3489 -- 11.2.3. CHANGED-ADDRESS]: https://tools.ietf.org/html/rfc3489#section-11.2.3
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChangedAddress(SocketAddr);
+
+impl ChangedAddress {
+    /// The codepoint of the type of the attribute.
+    pub const CODEPOINT: u16 = 0x0005;
+
+    /// Makes a new `ChangedAddress` instance.
+    pub fn new(addr: SocketAddr) -> Self {
+        ChangedAddress(addr)
+    }
+
+    /// Returns the address of this instance.
+    pub fn address(&self) -> SocketAddr {
+        self.0
+    }
+}
+
+impl Attribute for ChangedAddress {
+    type Decoder = ChangedAddressDecoder;
+    type Encoder = ChangedAddressEncoder;
+
+    fn get_type(&self) -> AttributeType {
+        AttributeType::new(Self::CODEPOINT)
+    }
+}
+
+/// [`ChangedAddress`] decoder.
+#[derive(Debug, Default)]
+pub struct ChangedAddressDecoder(SocketAddrDecoder);
+
+impl ChangedAddressDecoder {
+    /// Makes a new `ChangedAddressDecoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_decode!(ChangedAddressDecoder, ChangedAddress, |item| Ok(
+    ChangedAddress(item)
+));
+
+/// [`ChangedAddress`] encoder.
+#[derive(Debug, Default)]
+pub struct ChangedAddressEncoder(SocketAddrEncoder);
+
+impl ChangedAddressEncoder {
+    /// Makes a new `ChangedAddressEncoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_encode!(ChangedAddressEncoder, ChangedAddress, |item: Self::Item| {
+    item.0
+});
+
+/// `PASSWORD` attribute.
+///
+/// See [RFC 3489 -- 11.2.7. PASSWORD] about this attribute.
+///
+/// The password is zeroized when this is dropped.
+/// Note that each clone holds its own copy of the password, which is zeroized only when that clone is dropped.
+///
+/// The password is omitted from the `Debug` output and compared in constant time.
+///
+/// [RFC 3489 -- 11.2.7. PASSWORD]: https://tools.ietf.org/html/rfc3489#section-11.2.7
+#[derive(Clone)]
+pub struct Password(Zeroizing<Vec<u8>>);
+
+impl Password {
+    /// The codepoint of the type of the attribute.
+    pub const CODEPOINT: u16 = 0x0007;
+
+    /// Makes a new `Password` instance.
+    ///
+    /// # Errors
+    ///
+    /// The length of `password` must be a multiple of 4.
+    /// If it is not, this will return an `ErrorKind::InvalidInput` error.
+    pub fn new(password: Vec<u8>) -> Result<Self> {
+        let password = Zeroizing::new(password);
+        track_assert_eq!(password.len() % 4, 0, ErrorKind::InvalidInput; password.len());
+        Ok(Password(password))
+    }
+
+    /// Returns a reference to the password.
+    pub fn password(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Password {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Password(<{} bytes>)", self.0.len())
+    }
+}
+
+impl PartialEq for Password {
+    fn eq(&self, other: &Self) -> bool {
+        bool::from(self.0.ct_eq(&other.0))
+    }
+}
+
+impl Eq for Password {}
+
+impl Hash for Password {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0[..].hash(state);
+    }
+}
+
+impl Attribute for Password {
+    type Decoder = PasswordDecoder;
+    type Encoder = PasswordEncoder;
+
+    fn get_type(&self) -> AttributeType {
+        AttributeType::new(Self::CODEPOINT)
+    }
+}
+
+/// [`Password`] decoder.
+#[derive(Debug, Default)]
+pub struct PasswordDecoder(RemainingBytesDecoder);
+
+impl PasswordDecoder {
+    /// Makes a new `PasswordDecoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_decode!(PasswordDecoder, Password, Password::new);
+
+/// [`Password`] encoder.
+#[derive(Debug, Default)]
+pub struct PasswordEncoder(BytesEncoder<Zeroizing<Vec<u8>>>);
+
+impl PasswordEncoder {
+    /// Makes a new `PasswordEncoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_encode!(PasswordEncoder, Password, |item: Self::Item| item.0);
+
+/// `REFLECTED-FROM` attribute.
+///
+/// See [RFC 3489 -- 11.2.11. REFLECTED-FROM] about this attribute.
+///
+/// [RFC 3489 -- 11.2.11. REFLECTED-FROM]: https://tools.ietf.org/html/rfc3489#section-11.2.11
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReflectedFrom(SocketAddr);
+
+impl ReflectedFrom {
+    /// The codepoint of the type of the attribute.
+    pub const CODEPOINT: u16 = 0x000B;
+
+    /// Makes a new `ReflectedFrom` instance.
+    pub fn new(addr: SocketAddr) -> Self {
+        ReflectedFrom(addr)
+    }
+
+    /// Returns the address of this instance.
+    pub fn address(&self) -> SocketAddr {
+        self.0
+    }
+}
+
+impl Attribute for ReflectedFrom {
+    type Decoder = ReflectedFromDecoder;
+    type Encoder = ReflectedFromEncoder;
+
+    fn get_type(&self) -> AttributeType {
+        AttributeType::new(Self::CODEPOINT)
+    }
+}
+
+/// [`ReflectedFrom`] decoder.
+#[derive(Debug, Default)]
+pub struct ReflectedFromDecoder(SocketAddrDecoder);
+
+impl ReflectedFromDecoder {
+    /// Makes a new `ReflectedFromDecoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_decode!(ReflectedFromDecoder, ReflectedFrom, |item| Ok(
+    ReflectedFrom(item)
+));
+
+/// [`ReflectedFrom`] encoder.
+#[derive(Debug, Default)]
+pub struct ReflectedFromEncoder(SocketAddrEncoder);
+
+impl ReflectedFromEncoder {
+    /// Makes a new `ReflectedFromEncoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_encode!(ReflectedFromEncoder, ReflectedFrom, |item: Self::Item| item
+    .0);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rfc3489::Attribute as Rfc3489Attribute;
+    use crate::rfc5389::attributes::MappedAddress;
+    use crate::rfc5389::methods::BINDING;
+    use crate::{Message, MessageClass, MessageDecoder, MessageEncoder, TransactionId};
+    use bytecodec::{DecodeExt, EncodeExt};
+    use trackable::result::TestResult;
+
+    #[test]
+    fn classic_message_works() -> TestResult {
+        let transaction_id = [
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+            0x0f, 0x10,
+        ];
+        let mapped: SocketAddr = "192.0.2.1:32853".parse().unwrap();
+        let changed: SocketAddr = "192.0.2.3:3479".parse().unwrap();
+        let mut message = Message::<Rfc3489Attribute>::new_classic(
+            MessageClass::SuccessResponse,
+            BINDING,
+            transaction_id,
+        );
+        message.add_attribute(MappedAddress::new(mapped));
+        message.add_attribute(SourceAddress::new("192.0.2.2:3478".parse().unwrap()));
+        message.add_attribute(ChangedAddress::new(changed));
+        message.add_attribute(ReflectedFrom::new("198.51.100.1:5000".parse().unwrap()));
+        message.add_attribute(track!(Password::new(b"pass".to_vec()))?);
+        assert!(message.is_classic());
+        assert_eq!(message.classic_transaction_id(), transaction_id);
+
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+        assert_eq!(&bytes[4..20], &transaction_id[..]);
+
+        // The default decoder rejects classic messages
+        assert!(MessageDecoder::<Rfc3489Attribute>::new()
+            .decode_from_bytes(&bytes)
+            .is_err());
+
+        let decoded =
+            track!(MessageDecoder::<Rfc3489Attribute>::new_classic().decode_from_bytes(&bytes))?
+                .expect("never fails");
+        assert!(decoded.is_classic());
+        assert_eq!(decoded.classic_transaction_id(), transaction_id);
+        assert_eq!(
+            decoded
+                .get_attribute::<MappedAddress>()
+                .map(|a| a.address()),
+            Some(mapped)
+        );
+        assert_eq!(
+            decoded
+                .get_attribute::<ChangedAddress>()
+                .map(|a| a.address()),
+            Some(changed)
+        );
+        assert_eq!(
+            decoded.get_attribute::<Password>().map(|a| a.password()),
+            Some(&b"pass"[..])
+        );
+
+        // The classic decoder also accepts RFC 5389 messages
+        let message = Message::<Rfc3489Attribute>::new(
+            MessageClass::Request,
+            BINDING,
+            TransactionId::new([3; 12]),
+        );
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+        let decoded =
+            track!(MessageDecoder::<Rfc3489Attribute>::new_classic().decode_from_bytes(&bytes))?
+                .expect("never fails");
+        assert!(!decoded.is_classic());
+        assert_eq!(decoded.transaction_id(), TransactionId::new([3; 12]));
+
+        assert!(Password::new(b"foo".to_vec()).is_err());
+
+        let password = track!(Password::new(b"pass".to_vec()))?;
+        assert_eq!(format!("{:?}", password), "Password(<4 bytes>)");
+        assert_eq!(password, track!(Password::new(b"pass".to_vec()))?);
+        assert_ne!(password, track!(Password::new(b"word".to_vec()))?);
+        Ok(())
+    }
+}
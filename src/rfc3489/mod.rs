@@ -0,0 +1,38 @@
+//! [RFC 3489(Classic STUN)][RFC 3489] specific components.
+//!
+//! Messages of [RFC 3489] do not have the magic cookie and use 128-bit transaction IDs.
+//! Use [`MessageDecoder::new_classic`] and [`Message::new_classic`] to handle such messages.
+//!
+//! Note that `MESSAGE-INTEGRITY` of [RFC 3489] is computed differently from that of [RFC 5389],
+//! so the `check_*` methods of [`MessageIntegrity`] may fail for classic messages.
+//!
+//! [RFC 3489]: https://tools.ietf.org/html/rfc3489
+//! [RFC 5389]: https://tools.ietf.org/html/rfc5389
+//! [`MessageDecoder::new_classic`]: crate::MessageDecoder::new_classic
+//! [`Message::new_classic`]: crate::Message::new_classic
+use self::attributes::*;
+use crate::rfc5389::attributes::{
+    ErrorCode, MappedAddress, MessageIntegrity, UnknownAttributes, Username,
+};
+use crate::rfc5780::attributes::ChangeRequest;
+
+pub mod attributes;
+
+define_attribute_enums!(
+    Attribute,
+    AttributeDecoder,
+    AttributeEncoder,
+    [
+        MappedAddress,
+        ResponseAddress,
+        ChangeRequest,
+        SourceAddress,
+        ChangedAddress,
+        Username,
+        Password,
+        MessageIntegrity,
+        ErrorCode,
+        UnknownAttributes,
+        ReflectedFrom
+    ]
+);
@@ -1,10 +1,11 @@
 //! Attributes that are defined in [RFC 5389].
 //!
 //! [RFC 5389]: https://tools.ietf.org/html/rfc5389
-use crate::attribute::{Attribute, AttributeType};
-use crate::message::{Message, MessageEncoder};
+use crate::attribute::{Attribute, AttributeType, ATTRIBUTE_HEADER_LEN};
+use crate::message::{Message, MessageEncoder, MessageHeader};
 use crate::net::{socket_addr_xor, SocketAddrDecoder, SocketAddrEncoder};
 use crate::rfc5389::errors;
+use crate::rfc8489::attributes::{opaque_string, SecurityFeatures, Userhash};
 use bytecodec::bytes::{BytesEncoder, CopyableBytesDecoder, Utf8Decoder, Utf8Encoder};
 use bytecodec::combinator::{Collect, PreEncode, Repeat};
 use bytecodec::fixnum::{U16beDecoder, U16beEncoder, U32beDecoder, U32beEncoder};
@@ -13,12 +14,21 @@ use bytecodec::{
     ByteCount, Decode, Encode, EncodeExt, Eos, Error, ErrorKind, Result, SizedEncode,
     TryTaggedDecode,
 };
-use byteorder::{BigEndian, ByteOrder};
+use crc::{Crc, Digest, Table, CRC_32_ISO_HDLC};
 use hmac::{Hmac, Mac};
 use sha1::Sha1;
 use std::borrow::Cow;
+use std::cmp;
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::net::SocketAddr;
 use std::vec;
+use subtle::ConstantTimeEq;
+use trackable::error::ErrorKindExt;
+use zeroize::Zeroizing;
+
+static CRC32: Crc<u32, Table<16>> = Crc::<u32, Table<16>>::new(&CRC_32_ISO_HDLC);
+const FINGERPRINT_XOR: u32 = 0x5354_554e;
 
 macro_rules! impl_decode {
     ($decoder:ty, $item:ident, $and_then:expr) => {
@@ -239,6 +249,9 @@ impl_encode!(ErrorCodeEncoder, ErrorCode, |item: Self::Item| {
 ///
 /// See [RFC 5389 -- 15.5. FINGERPRINT] about this attribute.
 ///
+/// Note that [`MessageEncoder`] always overwrites the value of the last `FINGERPRINT` attribute of
+/// a message with the CRC-32 value of the message.
+///
 /// [RFC 5389 -- 15.5. FINGERPRINT]: https://tools.ietf.org/html/rfc5389#section-15.5
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Fingerprint {
@@ -249,12 +262,30 @@ impl Fingerprint {
     pub const CODEPOINT: u16 = 0x8028;
 
     /// Calculates the CRC-32 value of `message` and returns a `Fingerprint` instance containing it.
+    ///
+    /// The value is the one for the message to which this attribute is appended as the last attribute.
+    /// Note that it is not necessary to call this just to add `FINGERPRINT` to a message,
+    /// because [`MessageEncoder`] calculates the value anyway (see [`Fingerprint::placeholder`]).
     pub fn new<A: Attribute>(message: &Message<A>) -> Result<Self> {
         let mut bytes = track!(MessageEncoder::default().encode_into_bytes(message.clone()))?;
-        let final_len = bytes.len() as u16 - 20 + 8; // Adds `Fingerprint` attribute length
-        BigEndian::write_u16(&mut bytes[2..4], final_len);
-        let crc32 = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC).checksum(&bytes[..]) ^ 0x5354_554e;
-        Ok(Fingerprint { crc32 })
+        let message_len = bytes.len() - MessageHeader::SIZE + ATTRIBUTE_HEADER_LEN + 4;
+        track_assert!(message_len <= 0xFFFF, ErrorKind::InvalidInput; message_len);
+        bytes[2..4].copy_from_slice(&(message_len as u16).to_be_bytes());
+
+        let mut fingerprint = FingerprintDigest::new(bytes.len());
+        fingerprint.update(&bytes);
+        Ok(Fingerprint {
+            crc32: fingerprint.finalize(),
+        })
+    }
+
+    /// Makes a new `Fingerprint` instance whose value is to be calculated by [`MessageEncoder`].
+    ///
+    /// If the last attribute of a message is `FINGERPRINT`, [`MessageEncoder`] calculates its value
+    /// from the bytes preceding the attribute while encoding the message.
+    /// So the value of this instance is just a placeholder.
+    pub fn placeholder() -> Self {
+        Fingerprint { crc32: 0 }
     }
 
     /// Returns the crc32 value of this instance.
@@ -271,12 +302,53 @@ impl Attribute for Fingerprint {
     }
 
     fn after_decode<A: Attribute>(&mut self, message: &Message<A>) -> Result<()> {
-        let actual = track!(Self::new(message))?;
-        track_assert_eq!(actual.crc32, self.crc32, ErrorKind::InvalidInput);
+        let expected = track_assert_some!(
+            message.computed_fingerprint(),
+            ErrorKind::InvalidInput,
+            "FINGERPRINT must be the last attribute"
+        );
+        track_assert_eq!(expected, self.crc32, ErrorKind::InvalidInput);
         Ok(())
     }
 }
 
+/// Running CRC-32 calculation of the bytes preceding the `FINGERPRINT` attribute of a message.
+///
+/// The bytes following the first `len` bytes are ignored.
+#[derive(Clone)]
+pub(crate) struct FingerprintDigest {
+    digest: Digest<'static, u32, Table<16>>,
+    remaining: usize,
+}
+impl FingerprintDigest {
+    pub(crate) fn new(len: usize) -> Self {
+        FingerprintDigest {
+            digest: CRC32.digest(),
+            remaining: len,
+        }
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        let n = cmp::min(self.remaining, bytes.len());
+        self.digest.update(&bytes[..n]);
+        self.remaining -= n;
+    }
+
+    pub(crate) fn finalize(self) -> u32 {
+        self.digest.finalize() ^ FINGERPRINT_XOR
+    }
+}
+impl Default for FingerprintDigest {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+impl fmt::Debug for FingerprintDigest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FingerprintDigest {{ remaining: {} }}", self.remaining)
+    }
+}
+
 /// [`Fingerprint`] decoder.
 #[derive(Debug, Default)]
 pub struct FingerprintDecoder(U32beDecoder);
@@ -363,30 +435,44 @@ impl_encode!(MappedAddressEncoder, MappedAddress, |item: Self::Item| item
 ///
 /// [RFC 5389 -- 15.3. MESSAGE-INTEGRITY]: https://tools.ietf.org/html/rfc5389#section-15.4
 ///
-/// # TODO
-///
-/// - Support SASLprep
+/// Decoded instances do not hold any copy of the message.
+/// Instead, the `check_*` methods take the bytes of the received message and
+/// verify the HMAC value against them when called.
 ///
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Note that this is a breaking change in 0.5.0.
+/// In the earlier versions, the `check_*` methods did not take the message bytes
+/// (see `CHANGELOG.md` for migration).
+/// The given bytes must contain this attribute at the position where it was decoded;
+/// otherwise the `check_*` methods return an `Unauthorized` error.
+#[derive(Debug, Clone)]
 pub struct MessageIntegrity {
     hmac_sha1: [u8; 20],
-    preceding_message_bytes: Vec<u8>,
+    offset: Option<usize>,
 }
 impl MessageIntegrity {
     /// The codepoint of the type of the attribute.
     pub const CODEPOINT: u16 = 0x0008;
 
     /// utility function for creating HMAC-SHA1 signatures
-    fn generate_hmac_token(key: &[u8], message: &[u8]) -> [u8; 20] {
+    pub(crate) fn generate_hmac_token(
+        key: &[u8],
+        message_bytes: &[u8],
+        offset: usize,
+    ) -> Result<[u8; 20]> {
         // Create the hasher with the key. We can use expect for Hmac algorithms as they allow arbitrary key sizes.
         let mut hasher: Hmac<Sha1> =
             Mac::new_from_slice(key).expect("HMAC algoritms can take keys of any size");
 
         // hash the message
-        hasher.update(message);
+        track!(update_mac_with_preceding_bytes(
+            &mut hasher,
+            message_bytes,
+            offset,
+            20
+        ))?;
 
         // finalize the hash and convert to a static array
-        hasher.finalize().into_bytes().into()
+        Ok(hasher.finalize().into_bytes().into())
     }
 
     /// Makes a new `MessageIntegrity` instance for short-term credentials.
@@ -394,16 +480,28 @@ impl MessageIntegrity {
     where
         A: Attribute,
     {
-        let key = password.as_bytes();
-        let preceding_message_bytes = track!(Self::message_into_bytes(message.clone()))?;
-        let hmac_sha1 = Self::generate_hmac_token(key, &preceding_message_bytes);
-        Ok(MessageIntegrity {
-            hmac_sha1,
-            preceding_message_bytes,
-        })
+        track!(Self::new_with_key(message, password.as_bytes()))
     }
 
     /// Makes a new `MessageIntegrity` instance for long-term credentials.
+    ///
+    /// > key = MD5(username ":" realm ":" SASLprep(password))
+    /// >
+    /// > [RFC 5389 -- 15.4. MESSAGE-INTEGRITY]
+    ///
+    /// Only `password` is processed by SASLprep here.
+    /// `username` and `realm` are used as they are, so they should be normalized when they are made
+    /// (e.g., by using [`Username::new_saslprep`] and [`Realm::new_saslprep`]).
+    ///
+    /// Use [`Algorithm::long_term_key`] and [`MessageIntegrity::new_with_key`] to follow
+    /// the password algorithm negotiated by the `PASSWORD-ALGORITHM` attribute.
+    ///
+    /// # Errors
+    ///
+    /// If `password` is rejected by SASLprep, this will return an `ErrorKind::InvalidInput` error.
+    ///
+    /// [RFC 5389 -- 15.4. MESSAGE-INTEGRITY]: https://tools.ietf.org/html/rfc5389#section-15.4
+    /// [`Algorithm::long_term_key`]: crate::rfc8489::attributes::Algorithm::long_term_key
     pub fn new_long_term_credential<A>(
         message: &Message<A>,
         username: &Username,
@@ -413,41 +511,96 @@ impl MessageIntegrity {
     where
         A: Attribute,
     {
-        let key =
-            md5::compute(format!("{}:{}:{}", username.name(), realm.text(), password).as_bytes());
-        let preceding_message_bytes = track!(Self::message_into_bytes(message.clone()))?;
-        let hmac_sha1 = Self::generate_hmac_token(&key.0[..], &preceding_message_bytes);
+        let key = track!(Self::long_term_key(username, realm, password))?;
+        track!(Self::new_with_key(message, &key[..]))
+    }
+
+    /// Makes a new `MessageIntegrity` instance from the given key.
+    pub fn new_with_key<A>(message: &Message<A>, key: &[u8]) -> Result<Self>
+    where
+        A: Attribute,
+    {
+        let bytes = track!(MessageEncoder::default().encode_into_bytes(message.clone()))?;
+        let hmac_sha1 = track!(Self::generate_hmac_token(key, &bytes, bytes.len()))?;
         Ok(MessageIntegrity {
             hmac_sha1,
-            preceding_message_bytes,
+            offset: Some(bytes.len()),
         })
     }
 
     /// Checks whether this has the valid short-term credential for `password`.
+    ///
+    /// `message_bytes` must be the bytes of the received message that contains this attribute.
     pub fn check_short_term_credential(
         &self,
+        message_bytes: &[u8],
         password: &str,
     ) -> std::result::Result<(), ErrorCode> {
-        let key = password.as_bytes();
-        let expected = Self::generate_hmac_token(key, &self.preceding_message_bytes);
-        if self.hmac_sha1 == expected {
-            Ok(())
-        } else {
-            Err(errors::Unauthorized.into())
-        }
+        self.check_with_key(message_bytes, password.as_bytes())
     }
 
     /// Checks whether this has the valid long-term credential for `password`.
+    ///
+    /// The key is derived in the same way as [`MessageIntegrity::new_long_term_credential`].
+    /// If `password` is rejected by SASLprep, this will return an `Unauthorized` error.
+    ///
+    /// `message_bytes` must be the bytes of the received message that contains this attribute.
     pub fn check_long_term_credential(
         &self,
+        message_bytes: &[u8],
+        username: &Username,
+        realm: &Realm,
+        password: &str,
+    ) -> std::result::Result<(), ErrorCode> {
+        let key = Self::long_term_key(username, realm, password)
+            .map_err(|_| ErrorCode::from(errors::Unauthorized))?;
+        self.check_with_key(message_bytes, &key[..])
+    }
+
+    /// Checks whether this has the valid HMAC-SHA1 value for the long-term credential
+    /// identified by `userhash` (i.e., the `USERHASH` attribute is used instead of `USERNAME`).
+    ///
+    /// `username` is the one that the server found for `userhash`
+    /// (e.g., by using [`Userhash::lookup`]).
+    /// If `userhash` does not match `username` and `realm`, this will return
+    /// an `Unauthorized` error.
+    pub fn check_long_term_credential_with_userhash(
+        &self,
+        message_bytes: &[u8],
+        userhash: &Userhash,
         username: &Username,
         realm: &Realm,
         password: &str,
     ) -> std::result::Result<(), ErrorCode> {
-        let key =
-            md5::compute(format!("{}:{}:{}", username.name(), realm.text(), password).as_bytes());
-        let expected = Self::generate_hmac_token(&key.0[..], &self.preceding_message_bytes);
-        if self.hmac_sha1 == expected {
+        if !userhash.matches(username, realm) {
+            return Err(errors::Unauthorized.into());
+        }
+        self.check_long_term_credential(message_bytes, username, realm, password)
+    }
+
+    /// Checks whether this has the valid HMAC-SHA1 value for `key`.
+    ///
+    /// The values are compared in constant time.
+    /// The value is calculated from the bytes of `message_bytes` preceding this attribute.
+    /// So `message_bytes` must be the bytes of the received message that contains this attribute
+    /// (e.g., the bytes passed to [`MessageDecoder`] or [`MessageRef::as_bytes`]).
+    ///
+    /// If `message_bytes` does not have this attribute at the position where it was decoded
+    /// (e.g., the bytes of another message are given), this will return an `Unauthorized` error.
+    ///
+    /// [`MessageDecoder`]: crate::MessageDecoder
+    /// [`MessageRef::as_bytes`]: crate::MessageRef::as_bytes
+    pub fn check_with_key(
+        &self,
+        message_bytes: &[u8],
+        key: &[u8],
+    ) -> std::result::Result<(), ErrorCode> {
+        let offset = self.offset.ok_or(errors::Unauthorized)?;
+        check_attribute_at(message_bytes, offset, Self::CODEPOINT, &self.hmac_sha1)
+            .map_err(|_| ErrorCode::from(errors::Unauthorized))?;
+        let expected = Self::generate_hmac_token(key, message_bytes, offset)
+            .map_err(|_| ErrorCode::from(errors::Unauthorized))?;
+        if bool::from(self.hmac_sha1[..].ct_eq(&expected[..])) {
             Ok(())
         } else {
             Err(errors::Unauthorized.into())
@@ -459,11 +612,31 @@ impl MessageIntegrity {
         self.hmac_sha1
     }
 
-    fn message_into_bytes<A: Attribute>(message: Message<A>) -> Result<Vec<u8>> {
-        let mut bytes = track!(MessageEncoder::default().encode_into_bytes(message))?;
-        let adjusted_len = bytes.len() - 20 /*msg header*/+ 4 /*attr header*/ + 20 /*hmac*/;
-        BigEndian::write_u16(&mut bytes[2..4], adjusted_len as u16);
-        Ok(bytes)
+    fn long_term_key(
+        username: &Username,
+        realm: &Realm,
+        password: &str,
+    ) -> Result<Zeroizing<[u8; 16]>> {
+        let password = Zeroizing::new(track!(saslprep(password))?.into_owned());
+        let input = Zeroizing::new(format!(
+            "{}:{}:{}",
+            username.name(),
+            realm.text(),
+            *password
+        ));
+        Ok(Zeroizing::new(md5::compute(input.as_bytes()).0))
+    }
+}
+impl PartialEq for MessageIntegrity {
+    fn eq(&self, other: &Self) -> bool {
+        // The decoding position (`offset`) is not a part of the value
+        bool::from(self.hmac_sha1[..].ct_eq(&other.hmac_sha1[..]))
+    }
+}
+impl Eq for MessageIntegrity {}
+impl Hash for MessageIntegrity {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hmac_sha1.hash(state);
     }
 }
 impl Attribute for MessageIntegrity {
@@ -475,7 +648,7 @@ impl Attribute for MessageIntegrity {
     }
 
     fn after_decode<A: Attribute>(&mut self, message: &Message<A>) -> Result<()> {
-        self.preceding_message_bytes = track!(Self::message_into_bytes(message.clone()))?;
+        self.offset = message.attribute_offset();
         Ok(())
     }
 }
@@ -492,7 +665,7 @@ impl MessageIntegrityDecoder {
 impl_decode!(MessageIntegrityDecoder, MessageIntegrity, |hmac_sha1| Ok(
     MessageIntegrity {
         hmac_sha1,
-        preceding_message_bytes: Vec::new() // dummy
+        offset: None // dummy
     }
 ));
 
@@ -511,6 +684,61 @@ impl_encode!(
     |item: Self::Item| item.hmac_sha1
 );
 
+/// Checks that `message_bytes` has the attribute of which type is `codepoint` and
+/// value is `value` at `offset`.
+///
+/// This ties a decoded integrity attribute to the bytes of the message that it came from.
+pub(crate) fn check_attribute_at(
+    message_bytes: &[u8],
+    offset: usize,
+    codepoint: u16,
+    value: &[u8],
+) -> Result<()> {
+    let end = offset + ATTRIBUTE_HEADER_LEN + value.len();
+    track_assert!(
+        offset >= MessageHeader::SIZE && end <= message_bytes.len(),
+        ErrorKind::InvalidInput;
+        offset, end, message_bytes.len()
+    );
+    let attr = &message_bytes[offset..end];
+    track_assert_eq!(
+        u16::from_be_bytes([attr[0], attr[1]]),
+        codepoint,
+        ErrorKind::InvalidInput
+    );
+    track_assert_eq!(
+        usize::from(u16::from_be_bytes([attr[2], attr[3]])),
+        value.len(),
+        ErrorKind::InvalidInput
+    );
+    track_assert!(&attr[4..] == value, ErrorKind::InvalidInput);
+    Ok(())
+}
+
+/// Feeds the bytes of `message_bytes` preceding the attribute at `offset` into `mac`.
+///
+/// The length field of the header is adjusted as if the message ended with
+/// the attribute of which value is `value_len` bytes.
+pub(crate) fn update_mac_with_preceding_bytes<M: Mac>(
+    mac: &mut M,
+    message_bytes: &[u8],
+    offset: usize,
+    value_len: usize,
+) -> Result<()> {
+    track_assert!(
+        (MessageHeader::SIZE..=message_bytes.len()).contains(&offset),
+        ErrorKind::InvalidInput;
+        offset, message_bytes.len()
+    );
+    let adjusted_len = offset - MessageHeader::SIZE + ATTRIBUTE_HEADER_LEN + value_len;
+    track_assert!(adjusted_len <= 0xFFFF, ErrorKind::InvalidInput; adjusted_len);
+
+    mac.update(&message_bytes[..2]);
+    mac.update(&(adjusted_len as u16).to_be_bytes());
+    mac.update(&message_bytes[4..offset]);
+    Ok(())
+}
+
 /// `NONCE` attribute.
 ///
 /// See [RFC 5389 -- 15.8. NONCE] about this attribute.
@@ -540,10 +768,35 @@ impl Nonce {
         Ok(Nonce { value })
     }
 
+    /// Makes a new `Nonce` instance that starts with the [RFC 8489] nonce cookie.
+    ///
+    /// The resulting value is `NONCE_COOKIE` + base64 encoded `features` + `value`.
+    ///
+    /// # Errors
+    ///
+    /// The length of the resulting value must be less than `128` characters.
+    /// If it is too long, this will return an `ErrorKind::InvalidInput` error.
+    ///
+    /// [RFC 8489]: https://tools.ietf.org/html/rfc8489#section-9.2
+    pub fn with_security_features(features: SecurityFeatures, value: &str) -> Result<Self> {
+        let mut nonce = features.to_nonce_cookie();
+        nonce.push_str(value);
+        track!(Self::new(nonce))
+    }
+
     /// Returns the value of this instance.
     pub fn value(&self) -> &str {
         &self.value
     }
+
+    /// Returns the security features encoded in the nonce cookie of this instance.
+    ///
+    /// If the value does not start with the [RFC 8489] nonce cookie, this will return `None`.
+    ///
+    /// [RFC 8489]: https://tools.ietf.org/html/rfc8489#section-9.2
+    pub fn security_features(&self) -> Option<SecurityFeatures> {
+        SecurityFeatures::from_nonce(&self.value)
+    }
 }
 impl Attribute for Nonce {
     type Decoder = NonceDecoder;
@@ -581,11 +834,6 @@ impl_encode!(NonceEncoder, Nonce, |item: Self::Item| item.value);
 /// See [RFC 5389 -- 15.7. REALM] about this attribute.
 ///
 /// [RFC 5389 -- 15.7. REALM]: https://tools.ietf.org/html/rfc5389#section-15.7
-///
-/// # TODO
-///
-/// - Support SASLprep [RFC 4013]
-///
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Realm {
     text: String,
@@ -605,6 +853,41 @@ impl Realm {
         Ok(Realm { text })
     }
 
+    /// Makes a new `Realm` instance after processing `text` by using SASLprep ([RFC 4013]).
+    ///
+    /// > It MUST be a UTF-8 \[RFC3629\] encoded sequence of less than 128 characters
+    /// > (which can be as long as 763 bytes), and MUST have been processed using SASLprep \[RFC4013\].
+    /// >
+    /// > [RFC 5389 -- 15.7. REALM]
+    ///
+    /// # Errors
+    ///
+    /// If `text` is rejected by SASLprep or the result is too long,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    ///
+    /// [RFC 4013]: https://tools.ietf.org/html/rfc4013
+    /// [RFC 5389 -- 15.7. REALM]: https://tools.ietf.org/html/rfc5389#section-15.7
+    pub fn new_saslprep(text: &str) -> Result<Self> {
+        let text = track!(saslprep(text))?;
+        track!(Self::new(text.into_owned()))
+    }
+
+    /// Makes a new `Realm` instance after processing `text` by using the OpaqueString profile ([RFC 8265]).
+    ///
+    /// [RFC 8489] requires the realm to be processed by using this profile.
+    ///
+    /// # Errors
+    ///
+    /// If `text` is rejected by the profile or the result is too long,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    ///
+    /// [RFC 8265]: https://tools.ietf.org/html/rfc8265
+    /// [RFC 8489]: https://tools.ietf.org/html/rfc8489#section-14.9
+    pub fn new_opaque_string(text: &str) -> Result<Self> {
+        let text = track!(opaque_string(text))?;
+        track!(Self::new(text.into_owned()))
+    }
+
     /// Returns the text of this instance.
     pub fn text(&self) -> &str {
         &self.text
@@ -795,10 +1078,6 @@ impl_encode!(
 /// See [RFC 5389 -- 15.3. USERNAME] about this attribute.
 ///
 /// [RFC 5389 -- 15.3. USERNAME]: https://tools.ietf.org/html/rfc5389#section-15.3
-///
-/// # TODO
-///
-/// - Support SASLprep [RFC 4013]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Username {
     name: String,
@@ -818,6 +1097,41 @@ impl Username {
         Ok(Username { name })
     }
 
+    /// Makes a new `Username` instance after processing `name` by using SASLprep ([RFC 4013]).
+    ///
+    /// > It MUST contain a UTF-8 \[RFC3629\] encoded sequence of less than 513 bytes,
+    /// > and MUST have been processed using SASLprep \[RFC4013\].
+    /// >
+    /// > [RFC 5389 -- 15.3. USERNAME]
+    ///
+    /// # Errors
+    ///
+    /// If `name` is rejected by SASLprep or the result is too long,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    ///
+    /// [RFC 4013]: https://tools.ietf.org/html/rfc4013
+    /// [RFC 5389 -- 15.3. USERNAME]: https://tools.ietf.org/html/rfc5389#section-15.3
+    pub fn new_saslprep(name: &str) -> Result<Self> {
+        let name = track!(saslprep(name))?;
+        track!(Self::new(name.into_owned()))
+    }
+
+    /// Makes a new `Username` instance after processing `name` by using the OpaqueString profile ([RFC 8265]).
+    ///
+    /// [RFC 8489] requires the username to be processed by using this profile.
+    ///
+    /// # Errors
+    ///
+    /// If `name` is rejected by the profile or the result is too long,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    ///
+    /// [RFC 8265]: https://tools.ietf.org/html/rfc8265
+    /// [RFC 8489]: https://tools.ietf.org/html/rfc8489#section-14.3
+    pub fn new_opaque_string(name: &str) -> Result<Self> {
+        let name = track!(opaque_string(name))?;
+        track!(Self::new(name.into_owned()))
+    }
+
     /// Returns the name of this instance.
     pub fn name(&self) -> &str {
         &self.name
@@ -987,3 +1301,110 @@ impl_encode!(
     XorMappedAddress2,
     |item: Self::Item| item.0
 );
+
+fn saslprep(s: &str) -> Result<Cow<'_, str>> {
+    stringprep::saslprep(s).map_err(|e| {
+        ErrorKind::InvalidInput
+            .cause(format!("SASLprep: {e}"))
+            .into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rfc5389::methods::BINDING;
+    use crate::rfc8489::attributes::Algorithm;
+    use crate::{MessageClass, MessageDecoder, TransactionId};
+    use bytecodec::DecodeExt;
+    use trackable::result::TestResult;
+
+    #[test]
+    fn message_integrity_long_term_key_uses_username_and_realm_as_they_are() -> TestResult {
+        let username = track!(Username::new("ユーザ\u{00AD}".to_owned()))?;
+        let realm = track!(Realm::new("レルム\u{2168}".to_owned()))?;
+        let normalized_username = track!(Username::new_saslprep("ユーザ\u{00AD}"))?;
+        let normalized_realm = track!(Realm::new_saslprep("レルム\u{2168}"))?;
+        assert_eq!(normalized_username.name(), "ユーザ");
+        assert_eq!(normalized_realm.text(), "レルムIX");
+
+        let mut message: Message<crate::rfc5389::Attribute> =
+            Message::new(MessageClass::Request, BINDING, TransactionId::new([3; 12]));
+        message.add_attribute(track!(MessageIntegrity::new_long_term_credential(
+            &message,
+            &normalized_username,
+            &normalized_realm,
+            "pass\u{00AD}"
+        ))?);
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+        let decoded =
+            track!(MessageDecoder::<crate::rfc5389::Attribute>::new().decode_from_bytes(&bytes))?;
+        let decoded = track!(decoded.map_err(bytecodec::Error::from))?;
+        let mi = decoded
+            .get_attribute::<MessageIntegrity>()
+            .expect("never fails");
+
+        // key = MD5(username ":" realm ":" SASLprep(password))
+        let key = md5::compute("ユーザ:レルムIX:pass".as_bytes()).0;
+        assert!(mi.check_with_key(&bytes, &key).is_ok());
+        assert!(mi
+            .check_long_term_credential(&bytes, &normalized_username, &normalized_realm, "pass")
+            .is_ok());
+        assert!(mi
+            .check_long_term_credential(&bytes, &username, &realm, "pass")
+            .is_err());
+
+        // Prohibited output
+        assert!(Username::new_saslprep("user\u{0007}").is_err());
+        assert!(MessageIntegrity::long_term_key(&username, &realm, "pass\u{0007}").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn long_term_key_is_the_same_as_rfc8489_md5_one() -> TestResult {
+        let username = track!(Username::new_saslprep("user"))?;
+        let realm = track!(Realm::new_saslprep("example.org"))?;
+        let key = track!(MessageIntegrity::long_term_key(&username, &realm, "pass"))?;
+        let rfc8489_key = track!(Algorithm::Md5.long_term_key(&username, &realm, "pass"))?;
+        assert_eq!(key[..], rfc8489_key[..]);
+        Ok(())
+    }
+
+    #[test]
+    fn fingerprint_new_works() -> TestResult {
+        let mut message: Message<crate::rfc5389::Attribute> =
+            Message::new(MessageClass::Request, BINDING, TransactionId::new([3; 12]));
+        message.add_attribute(track!(Software::new("foo".to_owned()))?);
+        let fingerprint = track!(Fingerprint::new(&message))?;
+
+        message.add_attribute(Fingerprint::placeholder());
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+        assert_eq!(
+            bytes[bytes.len() - 4..],
+            fingerprint.crc32().to_be_bytes()[..]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn message_integrity_equality_ignores_offset() {
+        let a = MessageIntegrity {
+            hmac_sha1: [1; 20],
+            offset: Some(20),
+        };
+        let mut b = a.clone();
+        b.offset = Some(48);
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+
+        b.hmac_sha1[19] = 2;
+        assert_ne!(a, b);
+        assert_ne!(hash(&a), hash(&b));
+    }
+
+    fn hash<T: Hash>(x: &T) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        x.hash(&mut hasher);
+        hasher.finish()
+    }
+}
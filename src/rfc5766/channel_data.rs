@@ -0,0 +1,278 @@
+//! `ChannelData` message defined in [RFC 5766 -- 11.4. The ChannelData Message].
+//!
+//! [RFC 5766 -- 11.4. The ChannelData Message]: https://tools.ietf.org/html/rfc5766#section-11.4
+use crate::attribute::Padding;
+use crate::rfc5766::attributes::ChannelNumber;
+use bytecodec::bytes::{BytesDecoder, BytesEncoder, RemainingBytesDecoder};
+use bytecodec::combinator::{Length, Peekable};
+use bytecodec::fixnum::{U16beDecoder, U16beEncoder};
+use bytecodec::{ByteCount, Decode, Encode, Eos, ErrorKind, Result, SizedEncode};
+use std::cmp;
+
+/// `ChannelData` message.
+///
+/// A `ChannelData` message is not a STUN message.
+/// It carries application data between a TURN client and server over a bound channel.
+///
+/// See [RFC 5766 -- 11.4. The ChannelData Message] about this message.
+///
+/// [RFC 5766 -- 11.4. The ChannelData Message]: https://tools.ietf.org/html/rfc5766#section-11.4
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChannelData {
+    channel_number: ChannelNumber,
+    data: Vec<u8>,
+}
+impl ChannelData {
+    /// The maximum length of the application data.
+    pub const MAX_DATA_LEN: usize = 0xFFFF;
+
+    /// Makes a new `ChannelData` instance.
+    ///
+    /// # Errors
+    ///
+    /// If the length of `data` exceeds `ChannelData::MAX_DATA_LEN`,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    pub fn new(channel_number: ChannelNumber, data: Vec<u8>) -> Result<Self> {
+        track_assert!(data.len() <= Self::MAX_DATA_LEN, ErrorKind::InvalidInput; data.len());
+        Ok(ChannelData {
+            channel_number,
+            data,
+        })
+    }
+
+    /// Returns the channel number of the message.
+    pub fn channel_number(&self) -> ChannelNumber {
+        self.channel_number
+    }
+
+    /// Returns a reference to the application data of the message.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Takes ownership of the application data of the message.
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+/// [`ChannelData`] decoder.
+///
+/// Over UDP, the padding that follows the application data is optional and
+/// is skipped only if it is contained in the same buffer as the last part of the data.
+/// Over TCP (or TLS-over-TCP), the padding is mandatory and always consumed
+/// (see [RFC 5766 -- 11.5. Sending a ChannelData Message]).
+///
+/// [RFC 5766 -- 11.5. Sending a ChannelData Message]: https://tools.ietf.org/html/rfc5766#section-11.5
+#[derive(Debug, Default)]
+pub struct ChannelDataDecoder {
+    channel_number: U16beDecoder,
+    data_len: Peekable<U16beDecoder>,
+    data: Length<RemainingBytesDecoder>,
+    padding: BytesDecoder<Padding>,
+    stream: bool,
+}
+impl ChannelDataDecoder {
+    /// Makes a new `ChannelDataDecoder` instance for UDP.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes a new `ChannelDataDecoder` instance for stream transports (i.e., TCP and TLS-over-TCP).
+    pub fn new_stream() -> Self {
+        ChannelDataDecoder {
+            stream: true,
+            ..Self::default()
+        }
+    }
+}
+impl Decode for ChannelDataDecoder {
+    type Item = ChannelData;
+
+    fn decode(&mut self, buf: &[u8], eos: Eos) -> Result<usize> {
+        let mut offset = 0;
+        if !self.data_len.is_idle() {
+            bytecodec_try_decode!(self.channel_number, offset, buf, eos);
+            bytecodec_try_decode!(self.data_len, offset, buf, eos);
+
+            let data_len = *self.data_len.peek().expect("never fails");
+            track!(self.data.set_expected_bytes(u64::from(data_len)))?;
+            if self.stream {
+                self.padding.set_bytes(Padding::new(data_len as usize));
+            } else {
+                self.padding.set_bytes(Padding::default());
+            }
+        }
+        if !self.data.is_idle() {
+            offset += track!(self.data.decode(&buf[offset..], eos))?;
+            if !self.data.is_idle() {
+                return Ok(offset);
+            }
+            if !self.stream {
+                let data_len = *self.data_len.peek().expect("never fails");
+                let padding_len = Padding::new(data_len as usize).as_ref().len();
+                offset += cmp::min(padding_len, buf.len() - offset);
+            }
+        }
+        bytecodec_try_decode!(self.padding, offset, buf, eos);
+        Ok(offset)
+    }
+
+    fn finish_decoding(&mut self) -> Result<Self::Item> {
+        let channel_number = track!(self.channel_number.finish_decoding())?;
+        let _ = track!(self.data_len.finish_decoding())?;
+        let data = track!(self.data.finish_decoding())?;
+        let _ = track!(self.padding.finish_decoding())?;
+        let channel_number = track!(ChannelNumber::new(channel_number))?;
+        Ok(ChannelData {
+            channel_number,
+            data,
+        })
+    }
+
+    fn requiring_bytes(&self) -> ByteCount {
+        if self.data_len.is_idle() {
+            self.data
+                .requiring_bytes()
+                .add_for_decoding(self.padding.requiring_bytes())
+        } else {
+            self.channel_number
+                .requiring_bytes()
+                .add_for_decoding(self.data_len.requiring_bytes())
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        self.data_len.is_idle() && self.data.is_idle() && self.padding.is_idle()
+    }
+}
+
+/// [`ChannelData`] encoder.
+///
+/// The padding is appended to the application data only for stream transports.
+#[derive(Debug, Default)]
+pub struct ChannelDataEncoder {
+    channel_number: U16beEncoder,
+    data_len: U16beEncoder,
+    data: BytesEncoder,
+    padding: BytesEncoder<Padding>,
+    stream: bool,
+}
+impl ChannelDataEncoder {
+    /// Makes a new `ChannelDataEncoder` instance for UDP.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes a new `ChannelDataEncoder` instance for stream transports (i.e., TCP and TLS-over-TCP).
+    pub fn new_stream() -> Self {
+        ChannelDataEncoder {
+            stream: true,
+            ..Self::default()
+        }
+    }
+}
+impl Encode for ChannelDataEncoder {
+    type Item = ChannelData;
+
+    fn encode(&mut self, buf: &mut [u8], eos: Eos) -> Result<usize> {
+        let mut offset = 0;
+        bytecodec_try_encode!(self.channel_number, offset, buf, eos);
+        bytecodec_try_encode!(self.data_len, offset, buf, eos);
+        bytecodec_try_encode!(self.data, offset, buf, eos);
+        bytecodec_try_encode!(self.padding, offset, buf, eos);
+        Ok(offset)
+    }
+
+    fn start_encoding(&mut self, item: Self::Item) -> Result<()> {
+        let data_len = item.data.len();
+        track_assert!(data_len <= ChannelData::MAX_DATA_LEN, ErrorKind::InvalidInput; data_len);
+        track!(self
+            .channel_number
+            .start_encoding(item.channel_number.value()))?;
+        track!(self.data_len.start_encoding(data_len as u16))?;
+        track!(self.data.start_encoding(item.data))?;
+        if self.stream {
+            track!(self.padding.start_encoding(Padding::new(data_len)))?;
+        }
+        Ok(())
+    }
+
+    fn requiring_bytes(&self) -> ByteCount {
+        ByteCount::Finite(self.exact_requiring_bytes())
+    }
+
+    fn is_idle(&self) -> bool {
+        self.channel_number.is_idle()
+            && self.data_len.is_idle()
+            && self.data.is_idle()
+            && self.padding.is_idle()
+    }
+}
+impl SizedEncode for ChannelDataEncoder {
+    fn exact_requiring_bytes(&self) -> u64 {
+        self.channel_number.exact_requiring_bytes()
+            + self.data_len.exact_requiring_bytes()
+            + self.data.exact_requiring_bytes()
+            + self.padding.exact_requiring_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytecodec::{DecodeExt, EncodeExt};
+    use trackable::result::TestResult;
+
+    fn channel_data() -> ChannelData {
+        let channel_number = ChannelNumber::new(0x4001).expect("never fails");
+        ChannelData::new(channel_number, b"hello".to_vec()).expect("never fails")
+    }
+
+    #[test]
+    fn udp_channel_data_works() -> TestResult {
+        let bytes = track!(ChannelDataEncoder::new().encode_into_bytes(channel_data()))?;
+        assert_eq!(bytes, b"\x40\x01\x00\x05hello");
+
+        let decoded = track!(ChannelDataDecoder::new().decode_from_bytes(&bytes))?;
+        assert_eq!(decoded, channel_data());
+
+        // Padding is optional over UDP
+        let decoded =
+            track!(ChannelDataDecoder::new().decode_from_bytes(b"\x40\x01\x00\x05hello\0\0\0"))?;
+        assert_eq!(decoded, channel_data());
+
+        // Invalid channel number
+        assert!(ChannelDataDecoder::new()
+            .decode_from_bytes(b"\x30\x01\x00\x00")
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn stream_channel_data_works() -> TestResult {
+        let bytes = track!(ChannelDataEncoder::new_stream().encode_into_bytes(channel_data()))?;
+        assert_eq!(bytes, b"\x40\x01\x00\x05hello\0\0\0");
+
+        let mut decoder = ChannelDataDecoder::new_stream();
+        let mut stream = bytes.clone();
+        stream.extend_from_slice(&bytes);
+        let mut offset = 0;
+        for chunk in stream.chunks(3) {
+            offset += track!(decoder.decode(chunk, Eos::new(false)))?;
+            if decoder.is_idle() {
+                assert_eq!(track!(decoder.finish_decoding())?, channel_data());
+            }
+        }
+        assert_eq!(offset, stream.len());
+
+        let empty = ChannelData::new(ChannelNumber::min(), Vec::new()).expect("never fails");
+        let bytes = track!(ChannelDataEncoder::new_stream().encode_into_bytes(empty.clone()))?;
+        assert_eq!(bytes, b"\x40\x00\x00\x00");
+        assert_eq!(
+            track!(ChannelDataDecoder::new_stream().decode_from_bytes(&bytes))?,
+            empty
+        );
+        Ok(())
+    }
+}
@@ -4,6 +4,7 @@
 use self::attributes::*;
 
 pub mod attributes;
+pub mod channel_data;
 pub mod errors;
 pub mod methods;
 
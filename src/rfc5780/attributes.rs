@@ -3,11 +3,14 @@
 //! [RFC 5780]: https://tools.ietf.org/html/rfc5780
 
 use std::net::SocketAddr;
+use std::time::Duration;
 
+use bytecodec::bytes::{BytesEncoder, RemainingBytesDecoder};
 use bytecodec::fixnum::{U32beDecoder, U32beEncoder};
-use bytecodec::{ByteCount, Decode, Encode, Eos, Result, SizedEncode, TryTaggedDecode};
+use bytecodec::{ByteCount, Decode, Encode, Eos, ErrorKind, Result, SizedEncode, TryTaggedDecode};
 
 use crate::attribute::{Attribute, AttributeType};
+use crate::message::{Message, MessageEncoder};
 use crate::net::{SocketAddrDecoder, SocketAddrEncoder};
 
 macro_rules! impl_decode {
@@ -316,4 +319,263 @@ impl ResponsePortEncoder {
         Self::default()
     }
 }
-impl_encode!(ResponsePortEncoder, ResponsePort, |_item: Self::Item| 0u16);
+impl_encode!(
+    ResponsePortEncoder,
+    ResponsePort,
+    |item: Self::Item| u32::from(item.0) << 16
+);
+
+/// `PADDING` attribute.
+///
+/// See [RFC 5780 -- 7.6. PADDING] about this attribute.
+///
+/// [RFC 5780 -- 7.6. PADDING]: https://tools.ietf.org/html/rfc5780#section-7.6
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Padding(usize);
+
+impl Padding {
+    /// The codepoint of the type of the attribute.
+    pub const CODEPOINT: u16 = 0x0026;
+
+    /// Makes a new `Padding` instance that has `len` bytes of value.
+    ///
+    /// # Errors
+    ///
+    /// The value of `len` must be a multiple of `4` and less than `0x10000`.
+    /// If not, this will return an `ErrorKind::InvalidInput` error.
+    pub fn new(len: usize) -> Result<Self> {
+        track_assert_eq!(len % 4, 0, ErrorKind::InvalidInput; len);
+        track_assert!(len < 0x10000, ErrorKind::InvalidInput; len);
+        Ok(Padding(len))
+    }
+
+    /// Makes a new `Padding` instance that makes the encoded size of `message` be `total_size` bytes
+    /// when it is added to the message.
+    ///
+    /// Note that attributes that will be added after this one (e.g., `MESSAGE-INTEGRITY`)
+    /// are not taken into account.
+    ///
+    /// # Errors
+    ///
+    /// If `total_size` is not a multiple of `4` or it is too small to contain the `PADDING` attribute,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    pub fn with_total_size<A: Attribute>(message: &Message<A>, total_size: usize) -> Result<Self> {
+        let mut encoder = MessageEncoder::new();
+        track!(encoder.start_encoding(message.clone()))?;
+        let current_size = encoder.exact_requiring_bytes() as usize;
+        track_assert!(current_size + 4 <= total_size, ErrorKind::InvalidInput; current_size, total_size);
+        track!(Self::new(total_size - current_size - 4))
+    }
+
+    /// Returns the length of the value of this instance.
+    pub fn len(&self) -> usize {
+        self.0
+    }
+
+    /// Returns `true` if the value of this instance is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl Attribute for Padding {
+    type Decoder = PaddingDecoder;
+    type Encoder = PaddingEncoder;
+
+    fn get_type(&self) -> AttributeType {
+        AttributeType::new(Self::CODEPOINT)
+    }
+}
+
+/// [`Padding`] decoder.
+#[derive(Debug, Default)]
+pub struct PaddingDecoder(RemainingBytesDecoder);
+
+impl PaddingDecoder {
+    /// Makes a new `PaddingDecoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_decode!(PaddingDecoder, Padding, |item: Vec<u8>| Ok(Padding(
+    item.len()
+)));
+
+/// [`Padding`] encoder.
+#[derive(Debug, Default)]
+pub struct PaddingEncoder(BytesEncoder);
+
+impl PaddingEncoder {
+    /// Makes a new `PaddingEncoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_encode!(PaddingEncoder, Padding, |item: Self::Item| vec![0; item.0]);
+
+/// `CACHE-TIMEOUT` attribute.
+///
+/// See [RFC 5780 -- 7.7. CACHE-TIMEOUT] about this attribute.
+///
+/// [RFC 5780 -- 7.7. CACHE-TIMEOUT]: https://tools.ietf.org/html/rfc5780#section-7.7
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheTimeout(Duration);
+
+impl CacheTimeout {
+    /// The codepoint of the type of the attribute.
+    pub const CODEPOINT: u16 = 0x8027;
+
+    /// Makes a new `CacheTimeout` instance.
+    ///
+    /// Note that the nanoseconds part of `timeout` is ignored and always set to `0`.
+    ///
+    /// # Errors
+    ///
+    /// If the seconds part of `timeout` is greater than `0xFFFF_FFFF`,
+    /// this function will return an `ErrorKind::InvalidInput` error.
+    pub fn new(timeout: Duration) -> Result<Self> {
+        let timeout_seconds = timeout.as_secs();
+        track_assert!(timeout_seconds <= 0xFFFF_FFFF, ErrorKind::InvalidInput);
+        Ok(CacheTimeout(Duration::from_secs(timeout_seconds)))
+    }
+
+    /// Makes a new `CacheTimeout` instance from `u32` value.
+    pub fn from_u32(timeout_seconds: u32) -> Self {
+        CacheTimeout(Duration::from_secs(u64::from(timeout_seconds)))
+    }
+
+    /// Returns the estimated lifetime of NAT bindings.
+    pub fn timeout(&self) -> Duration {
+        self.0
+    }
+}
+
+impl Attribute for CacheTimeout {
+    type Decoder = CacheTimeoutDecoder;
+    type Encoder = CacheTimeoutEncoder;
+
+    fn get_type(&self) -> AttributeType {
+        AttributeType::new(Self::CODEPOINT)
+    }
+}
+
+/// [`CacheTimeout`] decoder.
+#[derive(Debug, Default)]
+pub struct CacheTimeoutDecoder(U32beDecoder);
+
+impl CacheTimeoutDecoder {
+    /// Makes a new `CacheTimeoutDecoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_decode!(CacheTimeoutDecoder, CacheTimeout, |item| Ok(
+    CacheTimeout::from_u32(item)
+));
+
+/// [`CacheTimeout`] encoder.
+#[derive(Debug, Default)]
+pub struct CacheTimeoutEncoder(U32beEncoder);
+
+impl CacheTimeoutEncoder {
+    /// Makes a new `CacheTimeoutEncoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_encode!(
+    CacheTimeoutEncoder,
+    CacheTimeout,
+    |item: Self::Item| item.0.as_secs() as u32
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rfc5389::methods::BINDING;
+    use crate::rfc5780::Attribute as Rfc5780Attribute;
+    use crate::{MessageClass, MessageDecoder, TransactionId};
+    use bytecodec::{DecodeExt, EncodeExt};
+    use trackable::result::TestResult;
+
+    #[test]
+    fn padding_works() -> TestResult {
+        let mut message = Message::<Rfc5780Attribute>::new(
+            MessageClass::Request,
+            BINDING,
+            TransactionId::new([0; 12]),
+        );
+        message.add_attribute(ChangeRequest::new(true, false));
+        let padding = track!(Padding::with_total_size(&message, 1500))?;
+        assert_eq!(padding.len(), 1500 - 20 - 8 - 4);
+        message.add_attribute(padding);
+
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+        assert_eq!(bytes.len(), 1500);
+
+        let decoded = track!(MessageDecoder::<Rfc5780Attribute>::new().decode_from_bytes(&bytes))?
+            .expect("never fails");
+        assert_eq!(
+            decoded.get_attribute::<Padding>().map(|a| a.len()),
+            Some(1468)
+        );
+
+        let message = Message::<Rfc5780Attribute>::new(
+            MessageClass::Request,
+            BINDING,
+            TransactionId::new([0; 12]),
+        );
+        assert!(Padding::with_total_size(&message, 22).is_err());
+        assert!(Padding::with_total_size(&message, 24).is_ok());
+        assert!(Padding::with_total_size(&message, 1498).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn response_port_works() -> TestResult {
+        let mut message = Message::<Rfc5780Attribute>::new(
+            MessageClass::Request,
+            BINDING,
+            TransactionId::new([0; 12]),
+        );
+        message.add_attribute(ResponsePort::new(5000));
+
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+        assert_eq!(
+            &bytes[20..],
+            [0x00, 0x27, 0x00, 0x04, 0x13, 0x88, 0x00, 0x00]
+        );
+
+        let decoded = track!(MessageDecoder::<Rfc5780Attribute>::new().decode_from_bytes(&bytes))?
+            .expect("never fails");
+        assert_eq!(
+            decoded.get_attribute::<ResponsePort>().map(|a| a.port()),
+            Some(5000)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cache_timeout_works() -> TestResult {
+        let mut message = Message::<Rfc5780Attribute>::new(
+            MessageClass::SuccessResponse,
+            BINDING,
+            TransactionId::new([0; 12]),
+        );
+        message.add_attribute(CacheTimeout::from_u32(120));
+
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+        assert_eq!(
+            &bytes[20..],
+            [0x80, 0x27, 0x00, 0x04, 0x00, 0x00, 0x00, 0x78]
+        );
+
+        let decoded = track!(MessageDecoder::<Rfc5780Attribute>::new().decode_from_bytes(&bytes))?
+            .expect("never fails");
+        assert_eq!(
+            decoded.get_attribute::<CacheTimeout>().map(|a| a.timeout()),
+            Some(Duration::from_secs(120))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,540 @@
+//! Sans-IO client that discovers the NAT behavior by following [RFC 5780 -- 4. Discovery Process].
+//!
+//! [RFC 5780 -- 4. Discovery Process]: https://tools.ietf.org/html/rfc5780#section-4
+use std::fmt;
+use std::net::SocketAddr;
+
+use bytecodec::{ErrorKind, Result};
+
+use crate::convert::TryAsRef;
+use crate::rfc5389::attributes::XorMappedAddress;
+use crate::rfc5389::methods::BINDING;
+use crate::rfc5780::attributes::{ChangeRequest, OtherAddress};
+use crate::{Attribute, Message, MessageClass, TransactionId};
+
+/// NAT mapping behavior.
+///
+/// See [RFC 5780 -- 4.3. Determining NAT Mapping Behavior] for details.
+///
+/// [RFC 5780 -- 4.3. Determining NAT Mapping Behavior]: https://tools.ietf.org/html/rfc5780#section-4.3
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MappingBehavior {
+    /// The same mapping is reused for all destinations.
+    EndpointIndependent,
+
+    /// The same mapping is reused for destinations that have the same IP address.
+    AddressDependent,
+
+    /// The same mapping is reused only for the same destination IP address and port.
+    AddressAndPortDependent,
+}
+impl fmt::Display for MappingBehavior {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MappingBehavior::EndpointIndependent => write!(f, "endpoint-independent mapping"),
+            MappingBehavior::AddressDependent => write!(f, "address-dependent mapping"),
+            MappingBehavior::AddressAndPortDependent => {
+                write!(f, "address and port-dependent mapping")
+            }
+        }
+    }
+}
+
+/// NAT filtering behavior.
+///
+/// See [RFC 5780 -- 4.4. Determining NAT Filtering Behavior] for details.
+///
+/// [RFC 5780 -- 4.4. Determining NAT Filtering Behavior]: https://tools.ietf.org/html/rfc5780#section-4.4
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilteringBehavior {
+    /// Packets from any endpoint are accepted.
+    EndpointIndependent,
+
+    /// Packets are accepted only from the IP addresses that the client has sent packets to.
+    AddressDependent,
+
+    /// Packets are accepted only from the IP addresses and ports that the client has sent packets to.
+    AddressAndPortDependent,
+}
+impl fmt::Display for FilteringBehavior {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilteringBehavior::EndpointIndependent => write!(f, "endpoint-independent filtering"),
+            FilteringBehavior::AddressDependent => write!(f, "address-dependent filtering"),
+            FilteringBehavior::AddressAndPortDependent => {
+                write!(f, "address and port-dependent filtering")
+            }
+        }
+    }
+}
+
+/// The result of the NAT behavior discovery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NatBehavior {
+    mapped_address: SocketAddr,
+    mapping: MappingBehavior,
+    filtering: FilteringBehavior,
+}
+impl NatBehavior {
+    /// Returns the mapped address of the client observed by the primary address of the server.
+    ///
+    /// If this is equal to the local address of the client, there is no NAT between the client and the server.
+    pub fn mapped_address(&self) -> SocketAddr {
+        self.mapped_address
+    }
+
+    /// Returns the mapping behavior of the NAT.
+    pub fn mapping(&self) -> MappingBehavior {
+        self.mapping
+    }
+
+    /// Returns the filtering behavior of the NAT.
+    pub fn filtering(&self) -> FilteringBehavior {
+        self.filtering
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Test {
+    // Test I (common to both mapping and filtering tests).
+    Primary,
+    // Filtering Test II: "change IP" and "change port".
+    FilteringChangeAddressAndPort,
+    // Filtering Test III: "change port".
+    FilteringChangePort,
+    // Mapping Test II: alternate IP address and primary port.
+    MappingAlternateAddress,
+    // Mapping Test III: alternate IP address and alternate port.
+    MappingAlternateAddressAndPort,
+}
+
+/// Sans-IO client that discovers the mapping and filtering behavior of NAT.
+///
+/// The client runs the tests described in [RFC 5780 -- 4. Discovery Process] one at a time
+/// (the filtering tests first, then the mapping tests):
+///
+/// 1. Call [`next_request`] to get the request and its destination, and send it by using your own socket.
+/// 2. Pass the received response and its source address to [`handle_response`].
+///    If no response has been received after retransmissions, call [`handle_timeout`] instead.
+/// 3. Repeat the above until [`behavior`] returns `Some(_)`.
+///
+/// Note that all the requests must be sent from the same local socket.
+///
+/// [RFC 5780 -- 4. Discovery Process]: https://tools.ietf.org/html/rfc5780#section-4
+/// [`next_request`]: NatBehaviorDiscovery::next_request
+/// [`handle_response`]: NatBehaviorDiscovery::handle_response
+/// [`handle_timeout`]: NatBehaviorDiscovery::handle_timeout
+/// [`behavior`]: NatBehaviorDiscovery::behavior
+#[derive(Debug, Clone)]
+pub struct NatBehaviorDiscovery {
+    server: SocketAddr,
+    test: Option<Test>,
+    pending: Option<TransactionId>,
+    expected_source: Option<SocketAddr>,
+    other_address: Option<SocketAddr>,
+    mapped_addresses: Vec<SocketAddr>,
+    filtering: Option<FilteringBehavior>,
+    behavior: Option<NatBehavior>,
+}
+impl NatBehaviorDiscovery {
+    /// Makes a new `NatBehaviorDiscovery` instance.
+    ///
+    /// `server` is the primary address of a STUN server that supports [RFC 5780].
+    ///
+    /// [RFC 5780]: https://tools.ietf.org/html/rfc5780
+    pub fn new(server: SocketAddr) -> Self {
+        NatBehaviorDiscovery {
+            server,
+            test: Some(Test::Primary),
+            pending: None,
+            expected_source: None,
+            other_address: None,
+            mapped_addresses: Vec::new(),
+            filtering: None,
+            behavior: None,
+        }
+    }
+
+    /// Returns the next request to be sent and its destination address.
+    ///
+    /// `transaction_id` is used as the transaction ID of the request and should be generated randomly.
+    ///
+    /// If the client is waiting for the response of the previous request or
+    /// the discovery has finished, this will return `None`.
+    pub fn next_request<A>(
+        &mut self,
+        transaction_id: TransactionId,
+    ) -> Option<(SocketAddr, Message<A>)>
+    where
+        A: Attribute + From<ChangeRequest>,
+    {
+        if self.pending.is_some() {
+            return None;
+        }
+        let test = self.test?;
+        let mut request = Message::new(MessageClass::Request, BINDING, transaction_id);
+        let (destination, expected_source) = match test {
+            Test::Primary => (self.server, self.server),
+            Test::MappingAlternateAddress => {
+                let other = self.other_address.expect("never fails");
+                let destination = SocketAddr::new(other.ip(), self.server.port());
+                (destination, destination)
+            }
+            Test::MappingAlternateAddressAndPort => {
+                let other = self.other_address.expect("never fails");
+                (other, other)
+            }
+            Test::FilteringChangeAddressAndPort => {
+                request.add_attribute(ChangeRequest::new(true, true));
+                (self.server, self.other_address.expect("never fails"))
+            }
+            Test::FilteringChangePort => {
+                request.add_attribute(ChangeRequest::new(false, true));
+                let other = self.other_address.expect("never fails");
+                (self.server, SocketAddr::new(self.server.ip(), other.port()))
+            }
+        };
+        self.pending = Some(transaction_id);
+        self.expected_source = Some(expected_source);
+        Some((destination, request))
+    }
+
+    /// Handles a response to the request returned by [`next_request`].
+    ///
+    /// `source` is the address from which the response was received.
+    ///
+    /// Responses that do not correspond to the outstanding request (e.g., duplicates caused by
+    /// retransmissions) are ignored.
+    ///
+    /// # Errors
+    ///
+    /// If the response is not a success response, it lacks the required attributes, or
+    /// the server does not support [RFC 5780], this will return an `ErrorKind::InvalidInput` error.
+    /// After an error, the discovery cannot be continued.
+    ///
+    /// Responses to the filtering tests must come from the address requested by `CHANGE-REQUEST`
+    /// (see [RFC 5780 -- 4.4. Determining NAT Filtering Behavior]).
+    /// So if the server ignores `CHANGE-REQUEST` and responds from its primary address,
+    /// this will return an error instead of reporting a filtering behavior.
+    ///
+    /// [`next_request`]: NatBehaviorDiscovery::next_request
+    /// [RFC 5780]: https://tools.ietf.org/html/rfc5780
+    /// [RFC 5780 -- 4.4. Determining NAT Filtering Behavior]: https://tools.ietf.org/html/rfc5780#section-4.4
+    pub fn handle_response<A>(&mut self, source: SocketAddr, response: &Message<A>) -> Result<()>
+    where
+        A: Attribute + TryAsRef<XorMappedAddress> + TryAsRef<OtherAddress>,
+    {
+        if self.pending != Some(response.transaction_id()) {
+            return Ok(());
+        }
+        self.pending = None;
+        let expected_source = self.expected_source.take();
+        let result = self.handle_response_inner(source, expected_source, response);
+        if result.is_err() {
+            self.test = None;
+        }
+        track!(result)
+    }
+
+    /// Handles the timeout of the request identified by `transaction_id`.
+    ///
+    /// This should be called when no response has been received after retransmissions.
+    ///
+    /// # Errors
+    ///
+    /// If the timed out request is not the one for filtering tests
+    /// (i.e., the server or the alternate addresses of it are unreachable),
+    /// this will return an `ErrorKind::Other` error.
+    /// After an error, the discovery cannot be continued.
+    pub fn handle_timeout(&mut self, transaction_id: TransactionId) -> Result<()> {
+        if self.pending != Some(transaction_id) {
+            return Ok(());
+        }
+        self.pending = None;
+        self.expected_source = None;
+        match self.test {
+            Some(Test::FilteringChangeAddressAndPort) => {
+                self.test = Some(Test::FilteringChangePort);
+            }
+            Some(Test::FilteringChangePort) => {
+                self.filtering = Some(FilteringBehavior::AddressAndPortDependent);
+                self.test = Some(Test::MappingAlternateAddress);
+            }
+            test => {
+                self.test = None;
+                track_panic!(ErrorKind::Other, "No response from the server"; test);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the discovered behavior of the NAT.
+    ///
+    /// If the discovery has not finished yet, this will return `None`.
+    pub fn behavior(&self) -> Option<NatBehavior> {
+        self.behavior
+    }
+
+    /// Returns `true` if the discovery has finished (successfully or not).
+    pub fn is_finished(&self) -> bool {
+        self.test.is_none()
+    }
+
+    fn handle_response_inner<A>(
+        &mut self,
+        source: SocketAddr,
+        expected_source: Option<SocketAddr>,
+        response: &Message<A>,
+    ) -> Result<()>
+    where
+        A: Attribute + TryAsRef<XorMappedAddress> + TryAsRef<OtherAddress>,
+    {
+        let test = track_assert_some!(self.test, ErrorKind::Other);
+        track_assert_eq!(
+            response.class(),
+            MessageClass::SuccessResponse,
+            ErrorKind::InvalidInput
+        );
+        track_assert!(Some(source) == expected_source,
+                      ErrorKind::InvalidInput,
+                      "The response came from an unexpected address \
+                       (the server may not follow CHANGE-REQUEST)";
+                      test, source, expected_source);
+
+        // The filtering tests are run before the mapping tests because sending requests to
+        // the alternate address of the server would make the NAT accept packets from it.
+        match test {
+            Test::Primary => {
+                let mapped = track!(Self::mapped_address(response))?;
+                let other = response
+                    .get_attribute::<OtherAddress>()
+                    .map(|a| a.address());
+                let other = track_assert_some!(other, ErrorKind::InvalidInput, "No OTHER-ADDRESS");
+                track_assert!(other.ip() != self.server.ip() && other.port() != self.server.port(),
+                              ErrorKind::InvalidInput,
+                              "The server does not have alternate IP address and port";
+                              self.server, other);
+                self.other_address = Some(other);
+                self.mapped_addresses.push(mapped);
+                self.test = Some(Test::FilteringChangeAddressAndPort);
+            }
+            Test::FilteringChangeAddressAndPort => {
+                self.filtering = Some(FilteringBehavior::EndpointIndependent);
+                self.test = Some(Test::MappingAlternateAddress);
+            }
+            Test::FilteringChangePort => {
+                self.filtering = Some(FilteringBehavior::AddressDependent);
+                self.test = Some(Test::MappingAlternateAddress);
+            }
+            Test::MappingAlternateAddress => {
+                let mapped = track!(Self::mapped_address(response))?;
+                if mapped == self.mapped_addresses[0] {
+                    self.finish(MappingBehavior::EndpointIndependent);
+                } else {
+                    self.mapped_addresses.push(mapped);
+                    self.test = Some(Test::MappingAlternateAddressAndPort);
+                }
+            }
+            Test::MappingAlternateAddressAndPort => {
+                let mapped = track!(Self::mapped_address(response))?;
+                if mapped == self.mapped_addresses[1] {
+                    self.finish(MappingBehavior::AddressDependent);
+                } else {
+                    self.finish(MappingBehavior::AddressAndPortDependent);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn mapped_address<A>(response: &Message<A>) -> Result<SocketAddr>
+    where
+        A: Attribute + TryAsRef<XorMappedAddress>,
+    {
+        let mapped = response
+            .get_attribute::<XorMappedAddress>()
+            .map(|a| a.address());
+        Ok(track_assert_some!(
+            mapped,
+            ErrorKind::InvalidInput,
+            "No XOR-MAPPED-ADDRESS"
+        ))
+    }
+
+    fn finish(&mut self, mapping: MappingBehavior) {
+        self.test = None;
+        self.behavior = Some(NatBehavior {
+            mapped_address: self.mapped_addresses[0],
+            mapping,
+            filtering: self.filtering.expect("never fails"),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rfc5780::attributes::ResponseOrigin;
+    use crate::test_util::TestAttribute;
+
+    const SERVER: &str = "192.0.2.1:3478";
+    const OTHER: &str = "192.0.2.2:3479";
+
+    // A simulated NAT that maps and filters by the given behaviors.
+    //
+    // The simulated server behind it ignores `CHANGE-REQUEST` if `ignores_change_request` is `true`.
+    struct Nat {
+        mapping: MappingBehavior,
+        filtering: FilteringBehavior,
+        ignores_change_request: bool,
+        sent: Vec<SocketAddr>,
+    }
+    impl Nat {
+        fn mapped_address(&mut self, destination: SocketAddr) -> SocketAddr {
+            self.sent.push(destination);
+            let port = match self.mapping {
+                MappingBehavior::EndpointIndependent => 50000,
+                MappingBehavior::AddressDependent => match destination.ip() {
+                    ip if ip == addr(SERVER).ip() => 50000,
+                    _ => 50001,
+                },
+                MappingBehavior::AddressAndPortDependent => 50000 + self.sent.len() as u16,
+            };
+            SocketAddr::new("198.51.100.1".parse().unwrap(), port)
+        }
+
+        fn accepts(&self, source: SocketAddr) -> bool {
+            match self.filtering {
+                FilteringBehavior::EndpointIndependent => true,
+                FilteringBehavior::AddressDependent => {
+                    self.sent.iter().any(|a| a.ip() == source.ip())
+                }
+                FilteringBehavior::AddressAndPortDependent => self.sent.contains(&source),
+            }
+        }
+    }
+
+    fn addr(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    fn discover(mapping: MappingBehavior, filtering: FilteringBehavior) -> Result<NatBehavior> {
+        track!(run(Nat {
+            mapping,
+            filtering,
+            ignores_change_request: false,
+            sent: Vec::new(),
+        }))
+    }
+
+    fn run(mut nat: Nat) -> Result<NatBehavior> {
+        let mut client = NatBehaviorDiscovery::new(addr(SERVER));
+        let mut i = 0;
+        while let Some((destination, request)) =
+            client.next_request::<TestAttribute>(TransactionId::new([i; 12]))
+        {
+            i += 1;
+            let mapped = nat.mapped_address(destination);
+            let (change_ip, change_port) = request
+                .get_attribute::<ChangeRequest>()
+                .filter(|_| !nat.ignores_change_request)
+                .map_or((false, false), |c| (c.ip(), c.port()));
+            let source = SocketAddr::new(
+                if change_ip {
+                    addr(OTHER).ip()
+                } else {
+                    destination.ip()
+                },
+                if change_port {
+                    addr(OTHER).port()
+                } else {
+                    destination.port()
+                },
+            );
+            if !nat.accepts(source) {
+                track!(client.handle_timeout(request.transaction_id()))?;
+                continue;
+            }
+
+            let mut response = Message::<TestAttribute>::new(
+                MessageClass::SuccessResponse,
+                BINDING,
+                request.transaction_id(),
+            );
+            response.add_attribute(XorMappedAddress::new(mapped));
+            response.add_attribute(ResponseOrigin::new(source));
+            response.add_attribute(OtherAddress::new(addr(OTHER)));
+            track!(client.handle_response(source, &response))?;
+        }
+        assert!(client.is_finished());
+        Ok(client.behavior().expect("never fails"))
+    }
+
+    #[test]
+    fn discovery_works() -> Result<()> {
+        let mappings = [
+            MappingBehavior::EndpointIndependent,
+            MappingBehavior::AddressDependent,
+            MappingBehavior::AddressAndPortDependent,
+        ];
+        let filterings = [
+            FilteringBehavior::EndpointIndependent,
+            FilteringBehavior::AddressDependent,
+            FilteringBehavior::AddressAndPortDependent,
+        ];
+        for &mapping in &mappings {
+            for &filtering in &filterings {
+                let behavior = track!(discover(mapping, filtering))?;
+                assert_eq!(behavior.mapping(), mapping);
+                assert_eq!(behavior.filtering(), filtering);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn discovery_fails_without_response() {
+        let mut client = NatBehaviorDiscovery::new(addr(SERVER));
+        let (destination, request) = client
+            .next_request::<TestAttribute>(TransactionId::new([0; 12]))
+            .unwrap();
+        assert_eq!(destination, addr(SERVER));
+        assert!(client
+            .next_request::<TestAttribute>(TransactionId::new([1; 12]))
+            .is_none());
+
+        // Unrelated timeouts are ignored.
+        assert!(client.handle_timeout(TransactionId::new([1; 12])).is_ok());
+
+        assert!(client.handle_timeout(request.transaction_id()).is_err());
+        assert!(client.is_finished());
+        assert!(client.behavior().is_none());
+    }
+
+    #[test]
+    fn discovery_fails_without_other_address() {
+        let mut client = NatBehaviorDiscovery::new(addr(SERVER));
+        let (_, request) = client
+            .next_request::<TestAttribute>(TransactionId::new([0; 12]))
+            .unwrap();
+        let mut response = Message::<TestAttribute>::new(
+            MessageClass::SuccessResponse,
+            BINDING,
+            request.transaction_id(),
+        );
+        response.add_attribute(XorMappedAddress::new(addr("198.51.100.1:50000")));
+        assert!(client.handle_response(addr(SERVER), &response).is_err());
+        assert!(client.is_finished());
+    }
+
+    #[test]
+    fn discovery_fails_if_server_ignores_change_request() {
+        let nat = Nat {
+            mapping: MappingBehavior::EndpointIndependent,
+            filtering: FilteringBehavior::EndpointIndependent,
+            ignores_change_request: true,
+            sent: Vec::new(),
+        };
+        assert!(run(nat).is_err());
+    }
+}
@@ -4,10 +4,19 @@
 use self::attributes::*;
 
 pub mod attributes;
+pub mod client;
+pub mod server;
 
 define_attribute_enums!(
     Attribute,
     AttributeDecoder,
     AttributeEncoder,
-    [ChangeRequest, ResponseOrigin, ResponsePort, OtherAddress]
+    [
+        ChangeRequest,
+        ResponseOrigin,
+        ResponsePort,
+        OtherAddress,
+        Padding,
+        CacheTimeout
+    ]
 );
@@ -0,0 +1,303 @@
+//! Sans-IO server that responds to the NAT behavior discovery requests described in [RFC 5780].
+//!
+//! [RFC 5780]: https://tools.ietf.org/html/rfc5780
+use std::net::{IpAddr, SocketAddr};
+
+use bytecodec::{ErrorKind, Result};
+
+use crate::convert::TryAsRef;
+use crate::rfc5389::attributes::XorMappedAddress;
+use crate::rfc5389::methods::BINDING;
+use crate::rfc5780::attributes::{ChangeRequest, OtherAddress, ResponseOrigin, ResponsePort};
+use crate::{Attribute, Message, MessageClass};
+
+/// A response to be sent by [`NatBehaviorServer`].
+#[derive(Debug, Clone)]
+pub struct BindingResponse<A> {
+    source: SocketAddr,
+    destination: SocketAddr,
+    message: Message<A>,
+}
+impl<A> BindingResponse<A> {
+    /// Returns the address of the server socket from which the response should be sent.
+    pub fn source(&self) -> SocketAddr {
+        self.source
+    }
+
+    /// Returns the address to which the response should be sent.
+    pub fn destination(&self) -> SocketAddr {
+        self.destination
+    }
+
+    /// Returns a reference to the response message.
+    pub fn message(&self) -> &Message<A> {
+        &self.message
+    }
+
+    /// Takes ownership of the response message.
+    pub fn into_message(self) -> Message<A> {
+        self.message
+    }
+}
+
+/// Sans-IO server that has two IP addresses and two ports (i.e., four sockets) and
+/// responds to Binding requests as described in [RFC 5780 -- 6. Server Behavior].
+///
+/// The server does not touch any sockets.
+/// The caller receives requests on the four sockets returned by [`NatBehaviorServer::sockets`],
+/// passes them to [`NatBehaviorServer::handle_request`], and sends the resulting response from
+/// the designated socket.
+///
+/// [RFC 5780 -- 6. Server Behavior]: https://tools.ietf.org/html/rfc5780#section-6
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NatBehaviorServer {
+    primary: SocketAddr,
+    alternate: SocketAddr,
+}
+impl NatBehaviorServer {
+    /// Makes a new `NatBehaviorServer` instance.
+    ///
+    /// The four sockets of the server are the combinations of the IP addresses and the ports of
+    /// `primary` and `alternate`.
+    ///
+    /// # Errors
+    ///
+    /// If `primary` and `alternate` have the same IP address or port, or their address families differ,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    pub fn new(primary: SocketAddr, alternate: SocketAddr) -> Result<Self> {
+        track_assert_ne!(primary.ip(), alternate.ip(), ErrorKind::InvalidInput);
+        track_assert_ne!(primary.port(), alternate.port(), ErrorKind::InvalidInput);
+        track_assert_eq!(
+            primary.is_ipv4(),
+            alternate.is_ipv4(),
+            ErrorKind::InvalidInput
+        );
+        Ok(NatBehaviorServer { primary, alternate })
+    }
+
+    /// Returns the primary address of the server.
+    pub fn primary(&self) -> SocketAddr {
+        self.primary
+    }
+
+    /// Returns the alternate address of the server.
+    pub fn alternate(&self) -> SocketAddr {
+        self.alternate
+    }
+
+    /// Returns the addresses of the four sockets of the server.
+    pub fn sockets(&self) -> [SocketAddr; 4] {
+        [
+            self.primary,
+            SocketAddr::new(self.primary.ip(), self.alternate.port()),
+            SocketAddr::new(self.alternate.ip(), self.primary.port()),
+            self.alternate,
+        ]
+    }
+
+    /// Handles a Binding request that was received on `local` socket from `peer`.
+    ///
+    /// The response has `XOR-MAPPED-ADDRESS`, `RESPONSE-ORIGIN` and `OTHER-ADDRESS` attributes.
+    /// The socket from which the response should be sent is chosen according to
+    /// the `CHANGE-REQUEST` attribute of the request.
+    /// If the request has `RESPONSE-PORT` attribute, the response is destined to that port.
+    ///
+    /// # Errors
+    ///
+    /// If `local` is not one of the sockets of the server or the request is not a Binding request,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    pub fn handle_request<A>(
+        &self,
+        local: SocketAddr,
+        peer: SocketAddr,
+        request: &Message<A>,
+    ) -> Result<BindingResponse<A>>
+    where
+        A: Attribute
+            + TryAsRef<ChangeRequest>
+            + TryAsRef<ResponsePort>
+            + From<XorMappedAddress>
+            + From<ResponseOrigin>
+            + From<OtherAddress>,
+    {
+        track_assert!(self.sockets().contains(&local), ErrorKind::InvalidInput; local);
+        track_assert_eq!(
+            request.class(),
+            MessageClass::Request,
+            ErrorKind::InvalidInput
+        );
+        track_assert_eq!(request.method(), BINDING, ErrorKind::InvalidInput);
+
+        let (change_ip, change_port) = request
+            .get_attribute::<ChangeRequest>()
+            .map_or((false, false), |a| (a.ip(), a.port()));
+        let source = SocketAddr::new(
+            if change_ip {
+                self.other_ip(local)
+            } else {
+                local.ip()
+            },
+            if change_port {
+                self.other_port(local)
+            } else {
+                local.port()
+            },
+        );
+        let destination = request
+            .get_attribute::<ResponsePort>()
+            .map_or(peer, |a| SocketAddr::new(peer.ip(), a.port()));
+
+        let mut message = Message::new(
+            MessageClass::SuccessResponse,
+            BINDING,
+            request.transaction_id(),
+        );
+        message.add_attribute(XorMappedAddress::new(peer));
+        message.add_attribute(ResponseOrigin::new(source));
+        message.add_attribute(OtherAddress::new(SocketAddr::new(
+            self.other_ip(local),
+            self.other_port(local),
+        )));
+        Ok(BindingResponse {
+            source,
+            destination,
+            message,
+        })
+    }
+
+    fn other_ip(&self, local: SocketAddr) -> IpAddr {
+        if local.ip() == self.primary.ip() {
+            self.alternate.ip()
+        } else {
+            self.primary.ip()
+        }
+    }
+
+    fn other_port(&self, local: SocketAddr) -> u16 {
+        if local.port() == self.primary.port() {
+            self.alternate.port()
+        } else {
+            self.primary.port()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rfc5780::client::{FilteringBehavior, MappingBehavior, NatBehaviorDiscovery};
+    use crate::test_util::TestAttribute;
+    use crate::{MessageDecoder, MessageEncoder, TransactionId};
+    use bytecodec::{DecodeExt, EncodeExt};
+    use trackable::result::TestResult;
+
+    fn addr(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    fn server() -> NatBehaviorServer {
+        NatBehaviorServer::new(addr("192.0.2.1:3478"), addr("192.0.2.2:3479")).unwrap()
+    }
+
+    fn request(change: Option<ChangeRequest>) -> Message<TestAttribute> {
+        let mut request = Message::new(MessageClass::Request, BINDING, TransactionId::new([1; 12]));
+        if let Some(change) = change {
+            request.add_attribute(change);
+        }
+        request
+    }
+
+    #[test]
+    fn server_works() -> TestResult {
+        let server = server();
+        let peer = addr("198.51.100.1:50000");
+        let local = addr("192.0.2.1:3479");
+
+        let cases = [
+            (None, "192.0.2.1:3479"),
+            (Some(ChangeRequest::new(false, true)), "192.0.2.1:3478"),
+            (Some(ChangeRequest::new(true, false)), "192.0.2.2:3479"),
+            (Some(ChangeRequest::new(true, true)), "192.0.2.2:3478"),
+        ];
+        for (change, source) in cases.iter().cloned() {
+            let response = track!(server.handle_request(local, peer, &request(change)))?;
+            assert_eq!(response.source(), addr(source));
+            assert_eq!(response.destination(), peer);
+
+            let bytes = track!(MessageEncoder::new().encode_into_bytes(response.into_message()))?;
+            let message = track!(MessageDecoder::<TestAttribute>::new().decode_from_bytes(&bytes))?
+                .expect("never fails");
+            assert_eq!(message.class(), MessageClass::SuccessResponse);
+            assert_eq!(
+                message
+                    .get_attribute::<XorMappedAddress>()
+                    .map(|a| a.address()),
+                Some(peer)
+            );
+            assert_eq!(
+                message
+                    .get_attribute::<ResponseOrigin>()
+                    .map(|a| a.address()),
+                Some(addr(source))
+            );
+            assert_eq!(
+                message.get_attribute::<OtherAddress>().map(|a| a.address()),
+                Some(addr("192.0.2.2:3478"))
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn response_port_works() -> TestResult {
+        let server = server();
+        let mut request = request(None);
+        request.add_attribute(ResponsePort::new(6000));
+        let response =
+            track!(server.handle_request(server.primary(), addr("198.51.100.1:50000"), &request))?;
+        assert_eq!(response.destination(), addr("198.51.100.1:6000"));
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_request_is_rejected() {
+        let server = server();
+        let peer = addr("198.51.100.1:50000");
+        assert!(server
+            .handle_request(addr("192.0.2.3:3478"), peer, &request(None))
+            .is_err());
+
+        let indication = Message::<TestAttribute>::new(
+            MessageClass::Indication,
+            BINDING,
+            TransactionId::new([1; 12]),
+        );
+        assert!(server
+            .handle_request(server.primary(), peer, &indication)
+            .is_err());
+
+        assert!(NatBehaviorServer::new(addr("192.0.2.1:3478"), addr("192.0.2.1:3479")).is_err());
+    }
+
+    #[test]
+    fn discovery_with_server_works() -> TestResult {
+        // No NAT between the client and the server.
+        let server = server();
+        let client_addr = addr("198.51.100.1:50000");
+        let mut client = NatBehaviorDiscovery::new(server.primary());
+        let mut i = 0;
+        while let Some((destination, request)) =
+            client.next_request::<TestAttribute>(TransactionId::new([i; 12]))
+        {
+            i += 1;
+            let response = track!(server.handle_request(destination, client_addr, &request))?;
+            assert_eq!(response.destination(), client_addr);
+            track!(client.handle_response(response.source(), response.message()))?;
+        }
+        let behavior = client.behavior().expect("never fails");
+        assert_eq!(behavior.mapped_address(), client_addr);
+        assert_eq!(behavior.mapping(), MappingBehavior::EndpointIndependent);
+        assert_eq!(behavior.filtering(), FilteringBehavior::EndpointIndependent);
+        Ok(())
+    }
+}
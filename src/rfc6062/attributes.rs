@@ -0,0 +1,156 @@
+//! Attributes that are defined in [RFC 6062 -- 6.2. New STUN Attributes].
+//!
+//! [RFC 6062 -- 6.2. New STUN Attributes]: https://tools.ietf.org/html/rfc6062#section-6.2
+use crate::attribute::{Attribute, AttributeType};
+use bytecodec::fixnum::{U32beDecoder, U32beEncoder};
+use bytecodec::{ByteCount, Decode, Encode, Eos, Result, SizedEncode, TryTaggedDecode};
+
+macro_rules! impl_decode {
+    ($decoder:ty, $item:ident, $and_then:expr) => {
+        impl Decode for $decoder {
+            type Item = $item;
+
+            fn decode(&mut self, buf: &[u8], eos: Eos) -> Result<usize> {
+                track!(self.0.decode(buf, eos))
+            }
+
+            fn finish_decoding(&mut self) -> Result<Self::Item> {
+                track!(self.0.finish_decoding()).and_then($and_then)
+            }
+
+            fn requiring_bytes(&self) -> ByteCount {
+                self.0.requiring_bytes()
+            }
+
+            fn is_idle(&self) -> bool {
+                self.0.is_idle()
+            }
+        }
+        impl TryTaggedDecode for $decoder {
+            type Tag = AttributeType;
+
+            fn try_start_decoding(&mut self, attr_type: Self::Tag) -> Result<bool> {
+                Ok(attr_type.as_u16() == $item::CODEPOINT)
+            }
+        }
+    };
+}
+
+macro_rules! impl_encode {
+    ($encoder:ty, $item:ty, $map_from:expr) => {
+        impl Encode for $encoder {
+            type Item = $item;
+
+            fn encode(&mut self, buf: &mut [u8], eos: Eos) -> Result<usize> {
+                track!(self.0.encode(buf, eos))
+            }
+
+            #[allow(clippy::redundant_closure_call)]
+            fn start_encoding(&mut self, item: Self::Item) -> Result<()> {
+                track!(self.0.start_encoding($map_from(item)))
+            }
+
+            fn requiring_bytes(&self) -> ByteCount {
+                self.0.requiring_bytes()
+            }
+
+            fn is_idle(&self) -> bool {
+                self.0.is_idle()
+            }
+        }
+        impl SizedEncode for $encoder {
+            fn exact_requiring_bytes(&self) -> u64 {
+                self.0.exact_requiring_bytes()
+            }
+        }
+    };
+}
+
+/// `CONNECTION-ID` attribute.
+///
+/// See [RFC 6062 -- 6.2.1. CONNECTION-ID] about this attribute.
+///
+/// [RFC 6062 -- 6.2.1. CONNECTION-ID]: https://tools.ietf.org/html/rfc6062#section-6.2.1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConnectionId(u32);
+impl ConnectionId {
+    /// The codepoint of the type of the attribute.
+    pub const CODEPOINT: u16 = 0x002A;
+
+    /// Makes a new `ConnectionId` instance.
+    pub fn new(id: u32) -> Self {
+        ConnectionId(id)
+    }
+
+    /// Returns the identifier of the connection.
+    pub fn value(self) -> u32 {
+        self.0
+    }
+}
+impl Attribute for ConnectionId {
+    type Decoder = ConnectionIdDecoder;
+    type Encoder = ConnectionIdEncoder;
+
+    fn get_type(&self) -> AttributeType {
+        AttributeType::new(Self::CODEPOINT)
+    }
+}
+
+/// [`ConnectionId`] decoder.
+#[derive(Debug, Default)]
+pub struct ConnectionIdDecoder(U32beDecoder);
+impl ConnectionIdDecoder {
+    /// Makes a new `ConnectionIdDecoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_decode!(ConnectionIdDecoder, ConnectionId, |item| Ok(ConnectionId(
+    item
+)));
+
+/// [`ConnectionId`] encoder.
+#[derive(Debug, Default)]
+pub struct ConnectionIdEncoder(U32beEncoder);
+impl ConnectionIdEncoder {
+    /// Makes a new `ConnectionIdEncoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_encode!(ConnectionIdEncoder, ConnectionId, |item: Self::Item| item.0);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rfc6062::methods::CONNECTION_BIND;
+    use crate::rfc6062::Attribute as Rfc6062Attribute;
+    use crate::{Message, MessageClass, MessageDecoder, MessageEncoder, TransactionId};
+    use bytecodec::{DecodeExt, EncodeExt};
+    use trackable::result::TestResult;
+
+    #[test]
+    fn connection_id_works() -> TestResult {
+        let mut message = Message::<Rfc6062Attribute>::new(
+            MessageClass::Request,
+            CONNECTION_BIND,
+            TransactionId::new([0; 12]),
+        );
+        message.add_attribute(ConnectionId::new(0x1234_5678));
+
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+        assert_eq!(
+            &bytes[20..],
+            [0x00, 0x2A, 0x00, 0x04, 0x12, 0x34, 0x56, 0x78]
+        );
+
+        let decoded = track!(MessageDecoder::<Rfc6062Attribute>::new().decode_from_bytes(&bytes))?
+            .expect("never fails");
+        assert_eq!(decoded.method(), CONNECTION_BIND);
+        assert_eq!(
+            decoded.get_attribute::<ConnectionId>().map(|a| a.value()),
+            Some(0x1234_5678)
+        );
+        Ok(())
+    }
+}
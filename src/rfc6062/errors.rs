@@ -0,0 +1,51 @@
+//! Error codes that are defined in [RFC 6062 -- 6.3. New STUN Error Codes].
+//!
+//! [RFC 6062 -- 6.3. New STUN Error Codes]: https://tools.ietf.org/html/rfc6062#section-6.3
+use crate::rfc5389::attributes::ErrorCode;
+
+/// `446`: "Connection Already Exists".
+///
+/// The server is already handling a Connect request for the allocation
+/// with the same `XOR-PEER-ADDRESS`.
+///
+/// See [RFC 6062 -- 5.2. Receiving a Connect Request] for details.
+///
+/// [RFC 6062 -- 5.2. Receiving a Connect Request]: https://tools.ietf.org/html/rfc6062#section-5.2
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConnectionAlreadyExists;
+impl ConnectionAlreadyExists {
+    /// The codepoint of the error.
+    pub const CODEPOINT: u16 = 446;
+}
+impl From<ConnectionAlreadyExists> for ErrorCode {
+    fn from(_: ConnectionAlreadyExists) -> Self {
+        ErrorCode::new(
+            ConnectionAlreadyExists::CODEPOINT,
+            "Connection Already Exists".to_owned(),
+        )
+        .expect("never fails")
+    }
+}
+
+/// `447`: "Connection Timeout or Failure".
+///
+/// The server failed to establish the TCP connection to the peer, or the attempt timed out.
+///
+/// See [RFC 6062 -- 5.2. Receiving a Connect Request] for details.
+///
+/// [RFC 6062 -- 5.2. Receiving a Connect Request]: https://tools.ietf.org/html/rfc6062#section-5.2
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConnectionTimeoutOrFailure;
+impl ConnectionTimeoutOrFailure {
+    /// The codepoint of the error.
+    pub const CODEPOINT: u16 = 447;
+}
+impl From<ConnectionTimeoutOrFailure> for ErrorCode {
+    fn from(_: ConnectionTimeoutOrFailure) -> Self {
+        ErrorCode::new(
+            ConnectionTimeoutOrFailure::CODEPOINT,
+            "Connection Timeout or Failure".to_owned(),
+        )
+        .expect("never fails")
+    }
+}
@@ -0,0 +1,19 @@
+//! Methods that are defined in [RFC 6062 -- 6.1. New STUN Methods].
+//!
+//! [RFC 6062 -- 6.1. New STUN Methods]: https://tools.ietf.org/html/rfc6062#section-6.1
+use crate::Method;
+
+/// Connect method.
+///
+/// Only request/response semantics defined.
+pub const CONNECT: Method = Method(0x00A);
+
+/// ConnectionBind method.
+///
+/// Only request/response semantics defined.
+pub const CONNECTION_BIND: Method = Method(0x00B);
+
+/// ConnectionAttempt method.
+///
+/// Only indication semantics defined.
+pub const CONNECTION_ATTEMPT: Method = Method(0x00C);
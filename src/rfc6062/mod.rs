@@ -0,0 +1,15 @@
+//! [RFC 6062(TURN-TCP)][RFC 6062] specific components.
+//!
+//! [RFC 6062]: https://tools.ietf.org/html/rfc6062
+use self::attributes::*;
+
+pub mod attributes;
+pub mod errors;
+pub mod methods;
+
+define_attribute_enums!(
+    Attribute,
+    AttributeDecoder,
+    AttributeEncoder,
+    [ConnectionId]
+);
@@ -0,0 +1,571 @@
+//! Attributes that are defined in [RFC 7635 -- 6. STUN Attributes].
+//!
+//! [RFC 7635 -- 6. STUN Attributes]: https://tools.ietf.org/html/rfc7635#section-6
+use crate::attribute::{Attribute, AttributeType};
+use crate::rfc5389::attributes::{ErrorCode, Username};
+use crate::rfc5389::errors;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use bytecodec::bytes::{BytesEncoder, RemainingBytesDecoder, Utf8Decoder, Utf8Encoder};
+use bytecodec::{ByteCount, Decode, Encode, Eos, ErrorKind, Result, SizedEncode, TryTaggedDecode};
+use byteorder::{BigEndian, ByteOrder};
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroizing;
+
+macro_rules! impl_decode {
+    ($decoder:ty, $item:ident, $and_then:expr) => {
+        impl Decode for $decoder {
+            type Item = $item;
+
+            fn decode(&mut self, buf: &[u8], eos: Eos) -> Result<usize> {
+                track!(self.0.decode(buf, eos))
+            }
+
+            fn finish_decoding(&mut self) -> Result<Self::Item> {
+                track!(self.0.finish_decoding()).and_then($and_then)
+            }
+
+            fn requiring_bytes(&self) -> ByteCount {
+                self.0.requiring_bytes()
+            }
+
+            fn is_idle(&self) -> bool {
+                self.0.is_idle()
+            }
+        }
+        impl TryTaggedDecode for $decoder {
+            type Tag = AttributeType;
+
+            fn try_start_decoding(&mut self, attr_type: Self::Tag) -> Result<bool> {
+                Ok(attr_type.as_u16() == $item::CODEPOINT)
+            }
+        }
+    };
+}
+
+macro_rules! impl_encode {
+    ($encoder:ty, $item:ty, $map_from:expr) => {
+        impl Encode for $encoder {
+            type Item = $item;
+
+            fn encode(&mut self, buf: &mut [u8], eos: Eos) -> Result<usize> {
+                track!(self.0.encode(buf, eos))
+            }
+
+            #[allow(clippy::redundant_closure_call)]
+            fn start_encoding(&mut self, item: Self::Item) -> Result<()> {
+                track!(self.0.start_encoding($map_from(item)))
+            }
+
+            fn requiring_bytes(&self) -> ByteCount {
+                self.0.requiring_bytes()
+            }
+
+            fn is_idle(&self) -> bool {
+                self.0.is_idle()
+            }
+        }
+        impl SizedEncode for $encoder {
+            fn exact_requiring_bytes(&self) -> u64 {
+                self.0.exact_requiring_bytes()
+            }
+        }
+    };
+}
+
+/// `ACCESS-TOKEN` attribute.
+///
+/// This attribute holds the encrypted form of an [`AccessTokenBlock`].
+///
+/// See [RFC 7635 -- 6.2. ACCESS-TOKEN] about this attribute.
+///
+/// [RFC 7635 -- 6.2. ACCESS-TOKEN]: https://tools.ietf.org/html/rfc7635#section-6.2
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AccessToken {
+    nonce: Vec<u8>,
+    encrypted_block: Vec<u8>,
+}
+impl AccessToken {
+    /// The codepoint of the type of the attribute.
+    pub const CODEPOINT: u16 = 0x001B;
+
+    /// Makes a new `AccessToken` instance.
+    ///
+    /// # Errors
+    ///
+    /// If the length of `nonce` is greater than `0xFFFF`, this will return an `ErrorKind::InvalidInput` error.
+    pub fn new(nonce: Vec<u8>, encrypted_block: Vec<u8>) -> Result<Self> {
+        track_assert!(nonce.len() <= 0xFFFF, ErrorKind::InvalidInput; nonce.len());
+        Ok(AccessToken {
+            nonce,
+            encrypted_block,
+        })
+    }
+
+    /// Makes a new `AccessToken` instance by encrypting `block` with AES-256-GCM.
+    ///
+    /// `key` is the long-term key shared between the authorization server and the STUN server,
+    /// and `server_name` is the name of the STUN server (it is used as the associated data of AEAD).
+    ///
+    /// Note that `nonce` must not be reused with the same key.
+    pub fn encrypt(
+        block: &AccessTokenBlock,
+        key: &[u8; 32],
+        nonce: [u8; 12],
+        server_name: &str,
+    ) -> Result<Self> {
+        let cipher = Aes256Gcm::new(key.into());
+        let payload = Payload {
+            msg: &block.to_bytes(),
+            aad: server_name.as_bytes(),
+        };
+        let encrypted_block = track_assert_some!(
+            cipher.encrypt(Nonce::from_slice(&nonce), payload).ok(),
+            ErrorKind::InvalidInput
+        );
+        track!(Self::new(nonce.to_vec(), encrypted_block))
+    }
+
+    /// Decrypts the encrypted block of this token with AES-256-GCM.
+    ///
+    /// # Errors
+    ///
+    /// If the nonce is not 12 bytes, the authentication of the block fails or
+    /// the decrypted block is malformed, this will return an `ErrorKind::InvalidInput` error.
+    pub fn decrypt(&self, key: &[u8; 32], server_name: &str) -> Result<AccessTokenBlock> {
+        track_assert_eq!(self.nonce.len(), 12, ErrorKind::InvalidInput);
+        let cipher = Aes256Gcm::new(key.into());
+        let payload = Payload {
+            msg: &self.encrypted_block,
+            aad: server_name.as_bytes(),
+        };
+        let block = Zeroizing::new(track_assert_some!(
+            cipher.decrypt(Nonce::from_slice(&self.nonce), payload).ok(),
+            ErrorKind::InvalidInput,
+            "Failed to decrypt the access token"
+        ));
+        track!(AccessTokenBlock::from_bytes(&block))
+    }
+
+    /// Validates this token and returns the decrypted block.
+    ///
+    /// The key is looked up from `key_store` by `kid` (the value of the `USERNAME` attribute of the request).
+    /// The token is valid if it can be decrypted by the key and it has not expired at `now`.
+    ///
+    /// The MAC key of the returned block should be used to check the `MESSAGE-INTEGRITY` attribute
+    /// (e.g., [`MessageIntegrity::check_with_key`]).
+    ///
+    /// If the validation fails, this will return an `Unauthorized` error.
+    ///
+    /// [`MessageIntegrity::check_with_key`]: crate::rfc5389::attributes::MessageIntegrity::check_with_key
+    pub fn validate<S>(
+        &self,
+        key_store: &S,
+        kid: &Username,
+        server_name: &str,
+        now: SystemTime,
+    ) -> std::result::Result<AccessTokenBlock, ErrorCode>
+    where
+        S: AccessTokenKeyStore + ?Sized,
+    {
+        let key = key_store
+            .get_key(kid.name())
+            .map(Zeroizing::new)
+            .ok_or_else(|| ErrorCode::from(errors::Unauthorized))?;
+        let block = self
+            .decrypt(&key, server_name)
+            .map_err(|_| ErrorCode::from(errors::Unauthorized))?;
+        if block.is_expired(now) {
+            return Err(errors::Unauthorized.into());
+        }
+        Ok(block)
+    }
+
+    /// Returns the nonce of this token.
+    pub fn nonce(&self) -> &[u8] {
+        &self.nonce
+    }
+
+    /// Returns the encrypted block of this token.
+    pub fn encrypted_block(&self) -> &[u8] {
+        &self.encrypted_block
+    }
+}
+impl Attribute for AccessToken {
+    type Decoder = AccessTokenDecoder;
+    type Encoder = AccessTokenEncoder;
+
+    fn get_type(&self) -> AttributeType {
+        AttributeType::new(Self::CODEPOINT)
+    }
+}
+
+/// [`AccessToken`] decoder.
+#[derive(Debug, Default)]
+pub struct AccessTokenDecoder(RemainingBytesDecoder);
+impl AccessTokenDecoder {
+    /// Makes a new `AccessTokenDecoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_decode!(AccessTokenDecoder, AccessToken, |bytes: Vec<u8>| {
+    track_assert!(bytes.len() >= 2, ErrorKind::InvalidInput; bytes.len());
+    let nonce_len = BigEndian::read_u16(&bytes) as usize;
+    track_assert!(bytes.len() >= 2 + nonce_len, ErrorKind::InvalidInput; bytes.len(), nonce_len);
+    Ok(AccessToken {
+        nonce: bytes[2..][..nonce_len].to_vec(),
+        encrypted_block: bytes[2 + nonce_len..].to_vec(),
+    })
+});
+
+/// [`AccessToken`] encoder.
+#[derive(Debug, Default)]
+pub struct AccessTokenEncoder(BytesEncoder);
+impl AccessTokenEncoder {
+    /// Makes a new `AccessTokenEncoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_encode!(AccessTokenEncoder, AccessToken, |item: Self::Item| {
+    let mut bytes = vec![0; 2];
+    BigEndian::write_u16(&mut bytes, item.nonce.len() as u16);
+    bytes.extend_from_slice(&item.nonce);
+    bytes.extend_from_slice(&item.encrypted_block);
+    bytes
+});
+
+/// The plaintext of the encrypted block of an [`AccessToken`].
+///
+/// See [RFC 7635 -- 6.2. ACCESS-TOKEN] about this structure.
+///
+/// The MAC key is zeroized when this is dropped.
+/// It is also omitted from the `Debug` output and compared in constant time.
+///
+/// [RFC 7635 -- 6.2. ACCESS-TOKEN]: https://tools.ietf.org/html/rfc7635#section-6.2
+#[derive(Clone)]
+pub struct AccessTokenBlock {
+    mac_key: Zeroizing<Vec<u8>>,
+    timestamp: u64,
+    lifetime: u32,
+}
+impl AccessTokenBlock {
+    /// Makes a new `AccessTokenBlock` instance.
+    ///
+    /// `timestamp` is truncated to the precision of the token (1/64000 seconds), and
+    /// the sub-second part of `lifetime` is ignored.
+    ///
+    /// # Errors
+    ///
+    /// If the length of `mac_key` is greater than `0xFFFF`, `timestamp` is not representable in 48-bit seconds
+    /// or the seconds part of `lifetime` is greater than `0xFFFF_FFFF`,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    pub fn new(mac_key: Vec<u8>, timestamp: SystemTime, lifetime: Duration) -> Result<Self> {
+        track_assert!(mac_key.len() <= 0xFFFF, ErrorKind::InvalidInput; mac_key.len());
+        let since_epoch = track_assert_some!(
+            timestamp.duration_since(UNIX_EPOCH).ok(),
+            ErrorKind::InvalidInput
+        );
+        track_assert!(since_epoch.as_secs() < 1 << 48, ErrorKind::InvalidInput; since_epoch);
+        track_assert!(lifetime.as_secs() <= 0xFFFF_FFFF, ErrorKind::InvalidInput; lifetime);
+
+        let fraction = u64::from(since_epoch.subsec_nanos()) * 64_000 / 1_000_000_000;
+        Ok(AccessTokenBlock {
+            mac_key: Zeroizing::new(mac_key),
+            timestamp: (since_epoch.as_secs() << 16) | fraction,
+            lifetime: lifetime.as_secs() as u32,
+        })
+    }
+
+    /// Returns the key used to calculate the HMAC value of the `MESSAGE-INTEGRITY` attribute.
+    pub fn mac_key(&self) -> &[u8] {
+        &self.mac_key
+    }
+
+    /// Returns the time when the token was issued.
+    pub fn timestamp(&self) -> SystemTime {
+        let seconds = self.timestamp >> 16;
+        let nanos = (self.timestamp & 0xFFFF) * 1_000_000_000 / 64_000;
+        UNIX_EPOCH + Duration::from_secs(seconds) + Duration::from_nanos(nanos)
+    }
+
+    /// Returns the lifetime of the token.
+    pub fn lifetime(&self) -> Duration {
+        Duration::from_secs(u64::from(self.lifetime))
+    }
+
+    /// Returns `true` if the token has expired at `now`.
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.timestamp() + self.lifetime() <= now
+    }
+
+    fn to_bytes(&self) -> Zeroizing<Vec<u8>> {
+        let mut bytes = Zeroizing::new(vec![0; 2 + self.mac_key.len() + 12]);
+        BigEndian::write_u16(&mut bytes, self.mac_key.len() as u16);
+        bytes[2..][..self.mac_key.len()].copy_from_slice(&self.mac_key);
+        let offset = 2 + self.mac_key.len();
+        BigEndian::write_u64(&mut bytes[offset..], self.timestamp);
+        BigEndian::write_u32(&mut bytes[offset + 8..], self.lifetime);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        track_assert!(bytes.len() >= 2, ErrorKind::InvalidInput; bytes.len());
+        let key_len = BigEndian::read_u16(bytes) as usize;
+        track_assert!(bytes.len() >= 2 + key_len + 12, ErrorKind::InvalidInput; bytes.len(), key_len);
+        let offset = 2 + key_len;
+        Ok(AccessTokenBlock {
+            mac_key: Zeroizing::new(bytes[2..offset].to_vec()),
+            timestamp: BigEndian::read_u64(&bytes[offset..]),
+            lifetime: BigEndian::read_u32(&bytes[offset + 8..]),
+        })
+    }
+}
+
+impl fmt::Debug for AccessTokenBlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "AccessTokenBlock {{ mac_key: <{} bytes>, timestamp: {}, lifetime: {} }}",
+            self.mac_key.len(),
+            self.timestamp,
+            self.lifetime
+        )
+    }
+}
+impl PartialEq for AccessTokenBlock {
+    fn eq(&self, other: &Self) -> bool {
+        bool::from(self.mac_key.ct_eq(&other.mac_key))
+            && self.timestamp == other.timestamp
+            && self.lifetime == other.lifetime
+    }
+}
+impl Eq for AccessTokenBlock {}
+
+/// Store of the long-term keys shared between authorization servers and the STUN server.
+///
+/// The keys are used to decrypt [`AccessToken`]s.
+pub trait AccessTokenKeyStore {
+    /// Returns the AES-256-GCM key identified by `kid`.
+    fn get_key(&self, kid: &str) -> Option<[u8; 32]>;
+}
+impl AccessTokenKeyStore for HashMap<String, [u8; 32]> {
+    fn get_key(&self, kid: &str) -> Option<[u8; 32]> {
+        self.get(kid).copied()
+    }
+}
+impl<F> AccessTokenKeyStore for F
+where
+    F: Fn(&str) -> Option<[u8; 32]>,
+{
+    fn get_key(&self, kid: &str) -> Option<[u8; 32]> {
+        self(kid)
+    }
+}
+
+/// `THIRD-PARTY-AUTHORIZATION` attribute.
+///
+/// See [RFC 7635 -- 6.1. THIRD-PARTY-AUTHORIZATION] about this attribute.
+///
+/// [RFC 7635 -- 6.1. THIRD-PARTY-AUTHORIZATION]: https://tools.ietf.org/html/rfc7635#section-6.1
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ThirdPartyAuthorization {
+    server_name: String,
+}
+impl ThirdPartyAuthorization {
+    /// The codepoint of the type of the attribute.
+    pub const CODEPOINT: u16 = 0x802E;
+
+    /// Makes a new `ThirdPartyAuthorization` instance.
+    pub fn new(server_name: String) -> Self {
+        ThirdPartyAuthorization { server_name }
+    }
+
+    /// Returns the name of the authorization server.
+    pub fn server_name(&self) -> &str {
+        &self.server_name
+    }
+}
+impl Attribute for ThirdPartyAuthorization {
+    type Decoder = ThirdPartyAuthorizationDecoder;
+    type Encoder = ThirdPartyAuthorizationEncoder;
+
+    fn get_type(&self) -> AttributeType {
+        AttributeType::new(Self::CODEPOINT)
+    }
+}
+
+/// [`ThirdPartyAuthorization`] decoder.
+#[derive(Debug, Default)]
+pub struct ThirdPartyAuthorizationDecoder(Utf8Decoder);
+impl ThirdPartyAuthorizationDecoder {
+    /// Makes a new `ThirdPartyAuthorizationDecoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_decode!(
+    ThirdPartyAuthorizationDecoder,
+    ThirdPartyAuthorization,
+    |server_name| Ok(ThirdPartyAuthorization { server_name })
+);
+
+/// [`ThirdPartyAuthorization`] encoder.
+#[derive(Debug, Default)]
+pub struct ThirdPartyAuthorizationEncoder(Utf8Encoder);
+impl ThirdPartyAuthorizationEncoder {
+    /// Makes a new `ThirdPartyAuthorizationEncoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_encode!(
+    ThirdPartyAuthorizationEncoder,
+    ThirdPartyAuthorization,
+    |item: Self::Item| item.server_name
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rfc5389::attributes::MessageIntegrity;
+    use crate::rfc5389::Attribute as Rfc5389Attribute;
+    use crate::rfc5766::methods::ALLOCATE;
+    use crate::rfc7635::Attribute as Rfc7635Attribute;
+    use crate::{Message, MessageClass, MessageDecoder, MessageEncoder, TransactionId};
+    use bytecodec::{DecodeExt, EncodeExt};
+    use trackable::result::TestResult;
+
+    const KEY: [u8; 32] = [7; 32];
+
+    #[test]
+    fn access_token_works() -> TestResult {
+        let issued = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        let block = track!(AccessTokenBlock::new(
+            vec![1; 32],
+            issued,
+            Duration::from_secs(3600)
+        ))?;
+        let token = track!(AccessToken::encrypt(
+            &block,
+            &KEY,
+            [3; 12],
+            "turn.example.com"
+        ))?;
+
+        let mut message = Message::<Rfc7635Attribute>::new(
+            MessageClass::Request,
+            ALLOCATE,
+            TransactionId::new([0; 12]),
+        );
+        message.add_attribute(token);
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+        let decoded = track!(MessageDecoder::<Rfc7635Attribute>::new().decode_from_bytes(&bytes))?
+            .expect("never fails");
+        let token = decoded.get_attribute::<AccessToken>().expect("never fails");
+        assert_eq!(token.nonce(), [3; 12]);
+
+        let decrypted = track!(token.decrypt(&KEY, "turn.example.com"))?;
+        assert_eq!(decrypted, block);
+        assert_eq!(decrypted.timestamp(), issued);
+        assert_eq!(decrypted.lifetime(), Duration::from_secs(3600));
+        assert!(!format!("{:?}", decrypted).contains("[1, 1"));
+
+        let other = track!(AccessTokenBlock::new(
+            vec![2; 32],
+            issued,
+            Duration::from_secs(3600)
+        ))?;
+        assert_ne!(decrypted, other);
+
+        assert!(token.decrypt(&[8; 32], "turn.example.com").is_err());
+        assert!(token.decrypt(&KEY, "other.example.com").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn access_token_validation_works() -> TestResult {
+        let issued = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        let block = track!(AccessTokenBlock::new(
+            vec![1; 32],
+            issued,
+            Duration::from_secs(3600)
+        ))?;
+        let token = track!(AccessToken::encrypt(
+            &block,
+            &KEY,
+            [3; 12],
+            "turn.example.com"
+        ))?;
+
+        let mut key_store = HashMap::new();
+        key_store.insert("kid".to_owned(), KEY);
+        let kid = track!(Username::new("kid".to_owned()))?;
+        let unknown = track!(Username::new("unknown".to_owned()))?;
+
+        let now = issued + Duration::from_secs(60);
+        let validated = token
+            .validate(&key_store, &kid, "turn.example.com", now)
+            .expect("valid token");
+        assert_eq!(validated.mac_key(), &[1; 32][..]);
+
+        let error = token
+            .validate(&key_store, &unknown, "turn.example.com", now)
+            .err();
+        assert_eq!(
+            error.map(|e| e.code()),
+            Some(errors::Unauthorized::CODEPOINT)
+        );
+
+        let expired = issued + Duration::from_secs(3600);
+        assert!(token
+            .validate(&key_store, &kid, "turn.example.com", expired)
+            .is_err());
+
+        let key_store = |kid: &str| if kid == "kid" { Some(KEY) } else { None };
+        assert!(token
+            .validate(&key_store, &kid, "turn.example.com", now)
+            .is_ok());
+
+        // The MAC key is used for `MESSAGE-INTEGRITY`.
+        let mut message = Message::<Rfc5389Attribute>::new(
+            MessageClass::Request,
+            ALLOCATE,
+            TransactionId::new([0; 12]),
+        );
+        let mi = track!(MessageIntegrity::new_with_key(
+            &message,
+            validated.mac_key()
+        ))?;
+        message.add_attribute(mi.clone());
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+        assert!(mi.check_with_key(&bytes, &[1; 32]).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn third_party_authorization_works() -> TestResult {
+        let mut message = Message::<Rfc7635Attribute>::new(
+            MessageClass::ErrorResponse,
+            ALLOCATE,
+            TransactionId::new([0; 12]),
+        );
+        message.add_attribute(ThirdPartyAuthorization::new(
+            "https://auth.example.com".to_owned(),
+        ));
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+        let decoded = track!(MessageDecoder::<Rfc7635Attribute>::new().decode_from_bytes(&bytes))?
+            .expect("never fails");
+        assert_eq!(
+            decoded
+                .get_attribute::<ThirdPartyAuthorization>()
+                .map(|a| a.server_name()),
+            Some("https://auth.example.com")
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,13 @@
+//! [RFC 7635(STUN Extension for Third-Party Authorization)][RFC 7635] specific components.
+//!
+//! [RFC 7635]: https://tools.ietf.org/html/rfc7635
+use self::attributes::*;
+
+pub mod attributes;
+
+define_attribute_enums!(
+    Attribute,
+    AttributeDecoder,
+    AttributeEncoder,
+    [AccessToken, ThirdPartyAuthorization]
+);
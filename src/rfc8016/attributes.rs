@@ -106,11 +106,11 @@ impl Attribute for MobilityTicket {
     }
 }
 
-/// [`Data`] decoder.
+/// [`MobilityTicket`] decoder.
 #[derive(Debug, Default)]
 pub struct MobilityTicketDecoder(RemainingBytesDecoder);
 impl MobilityTicketDecoder {
-    /// Makes a new `DataDecoder` instance.
+    /// Makes a new `MobilityTicketDecoder` instance.
     pub fn new() -> Self {
         Self::default()
     }
@@ -119,11 +119,11 @@ impl_decode!(MobilityTicketDecoder, MobilityTicket, |item| Ok(
     MobilityTicket(item)
 ));
 
-/// [`Data`] encoder.
+/// [`MobilityTicket`] encoder.
 #[derive(Debug, Default)]
 pub struct MobilityTicketEncoder(BytesEncoder);
 impl MobilityTicketEncoder {
-    /// Makes a new `DataEncoder` instance.
+    /// Makes a new `MobilityTicketEncoder` instance.
     pub fn new() -> Self {
         Self::default()
     }
@@ -1,6 +1,119 @@
 //! [RFC 8016(Mobility with Traversal Using Relays around NAT (TURN))][RFC 8016] specific components.
 //!
 //! [RFC 8016]: https://tools.ietf.org/html/rfc8016
+use self::attributes::*;
+use crate::rfc5766::attributes::RequestedTransport;
+use crate::rfc5766::methods::{ALLOCATE, REFRESH};
+use crate::{Message, MessageClass, TransactionId};
 
 pub mod attributes;
 pub mod errors;
+
+define_attribute_enums!(
+    Attribute,
+    AttributeDecoder,
+    AttributeEncoder,
+    [MobilityTicket]
+);
+
+/// Makes a mobility-enabled Allocate request.
+///
+/// The request has `REQUESTED-TRANSPORT` attribute for `protocol` (e.g., `17` for UDP) and
+/// an empty `MOBILITY-TICKET` attribute.
+///
+/// > In order to provide the mobility functionality, the client MUST
+/// > include the MOBILITY-TICKET attribute, with length zero, in the
+/// > Allocate request.
+/// >
+/// > [RFC 8016 -- 3.1. Creating an Allocation]
+///
+/// Note that the caller should append credentials (e.g., `MESSAGE-INTEGRITY`) to the resulting message.
+///
+/// [RFC 8016 -- 3.1. Creating an Allocation]: https://tools.ietf.org/html/rfc8016#section-3.1
+pub fn allocate_request<A>(transaction_id: TransactionId, protocol: u8) -> Message<A>
+where
+    A: crate::Attribute + From<RequestedTransport> + From<MobilityTicket>,
+{
+    let mut request = Message::new(MessageClass::Request, ALLOCATE, transaction_id);
+    request.add_attribute(RequestedTransport::new(protocol));
+    request.add_attribute(MobilityTicket::empty());
+    request
+}
+
+/// Makes a Refresh request that carries `ticket`.
+///
+/// This is used to refresh the allocation after the IP address of the client has changed.
+///
+/// > If a client wants to refresh an existing allocation and update its
+/// > time-to-expiry or delete an existing allocation, it sends a Refresh
+/// > request as described in Section 7.1 of \[RFC5766\].  If the client
+/// > wants to retain the existing allocation in case of IP change, it MUST
+/// > include the MOBILITY-TICKET attribute received in the Allocate or
+/// > Refresh response.
+/// >
+/// > [RFC 8016 -- 3.2. Refreshing an Allocation]
+///
+/// Note that the caller should append `LIFETIME` and credentials to the resulting message if needed.
+///
+/// [RFC 8016 -- 3.2. Refreshing an Allocation]: https://tools.ietf.org/html/rfc8016#section-3.2
+pub fn refresh_request<A>(transaction_id: TransactionId, ticket: MobilityTicket) -> Message<A>
+where
+    A: crate::Attribute + From<MobilityTicket>,
+{
+    let mut request = Message::new(MessageClass::Request, REFRESH, transaction_id);
+    request.add_attribute(ticket);
+    request
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rfc5766::attributes::Lifetime;
+    use crate::test_util::TestAttribute;
+    use crate::{MessageDecoder, MessageEncoder};
+    use bytecodec::{DecodeExt, EncodeExt};
+    use std::time::Duration;
+    use trackable::result::TestResult;
+
+    #[test]
+    fn allocate_request_works() -> TestResult {
+        let request: Message<TestAttribute> = allocate_request(TransactionId::new([1; 12]), 17);
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(request))?;
+        let decoded = track!(MessageDecoder::<TestAttribute>::new().decode_from_bytes(&bytes))?
+            .expect("never fails");
+        assert_eq!(decoded.method(), ALLOCATE);
+        assert_eq!(
+            decoded
+                .get_attribute::<RequestedTransport>()
+                .map(|a| a.protocol()),
+            Some(17)
+        );
+        assert_eq!(
+            decoded.get_attribute::<MobilityTicket>().map(|a| a.data()),
+            Some(&[][..])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn refresh_request_works() -> TestResult {
+        let ticket = track!(MobilityTicket::new(vec![1, 2, 3, 4, 5]))?;
+        let mut request: Message<TestAttribute> =
+            refresh_request(TransactionId::new([2; 12]), ticket);
+        request.add_attribute(Lifetime::from_u32(600));
+
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(request))?;
+        let decoded = track!(MessageDecoder::<TestAttribute>::new().decode_from_bytes(&bytes))?
+            .expect("never fails");
+        assert_eq!(decoded.method(), REFRESH);
+        assert_eq!(
+            decoded.get_attribute::<MobilityTicket>().map(|a| a.data()),
+            Some(&[1, 2, 3, 4, 5][..])
+        );
+        assert_eq!(
+            decoded.get_attribute::<Lifetime>().map(|a| a.lifetime()),
+            Some(Duration::from_secs(600))
+        );
+        Ok(())
+    }
+}
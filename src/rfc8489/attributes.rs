@@ -0,0 +1,1231 @@
+//! Attributes that are defined in [RFC 8489].
+//!
+//! [RFC 8489]: https://tools.ietf.org/html/rfc8489
+use crate::attribute::{Attribute, AttributeType};
+use crate::message::{Message, MessageEncoder};
+use crate::rfc5389::attributes::{
+    check_attribute_at, update_mac_with_preceding_bytes, ErrorCode, Realm, Username,
+};
+use crate::rfc5389::errors;
+use bytecodec::bytes::{
+    BytesEncoder, CopyableBytesDecoder, RemainingBytesDecoder, Utf8Decoder, Utf8Encoder,
+};
+use bytecodec::{
+    ByteCount, Decode, Encode, EncodeExt, Eos, ErrorKind, Result, SizedEncode, TryTaggedDecode,
+};
+use byteorder::{BigEndian, ByteOrder};
+use hmac::{Hmac, Mac};
+use precis_profiles::precis_core::profile::PrecisFastInvocation;
+use precis_profiles::OpaqueString;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::hash::{Hash, Hasher};
+use subtle::ConstantTimeEq;
+use trackable::error::ErrorKindExt;
+use zeroize::Zeroizing;
+
+macro_rules! impl_decode {
+    ($decoder:ty, $item:ident, $and_then:expr) => {
+        impl Decode for $decoder {
+            type Item = $item;
+
+            fn decode(&mut self, buf: &[u8], eos: Eos) -> Result<usize> {
+                track!(self.0.decode(buf, eos))
+            }
+
+            fn finish_decoding(&mut self) -> Result<Self::Item> {
+                track!(self.0.finish_decoding()).and_then($and_then)
+            }
+
+            fn requiring_bytes(&self) -> ByteCount {
+                self.0.requiring_bytes()
+            }
+
+            fn is_idle(&self) -> bool {
+                self.0.is_idle()
+            }
+        }
+        impl TryTaggedDecode for $decoder {
+            type Tag = AttributeType;
+
+            fn try_start_decoding(&mut self, attr_type: Self::Tag) -> Result<bool> {
+                Ok(attr_type.as_u16() == $item::CODEPOINT)
+            }
+        }
+    };
+}
+
+macro_rules! impl_encode {
+    ($encoder:ty, $item:ty, $map_from:expr) => {
+        impl Encode for $encoder {
+            type Item = $item;
+
+            fn encode(&mut self, buf: &mut [u8], eos: Eos) -> Result<usize> {
+                track!(self.0.encode(buf, eos))
+            }
+
+            #[allow(clippy::redundant_closure_call)]
+            fn start_encoding(&mut self, item: Self::Item) -> Result<()> {
+                track!(self.0.start_encoding($map_from(item)))
+            }
+
+            fn requiring_bytes(&self) -> ByteCount {
+                self.0.requiring_bytes()
+            }
+
+            fn is_idle(&self) -> bool {
+                self.0.is_idle()
+            }
+        }
+        impl SizedEncode for $encoder {
+            fn exact_requiring_bytes(&self) -> u64 {
+                self.0.exact_requiring_bytes()
+            }
+        }
+    };
+}
+
+/// `MESSAGE-INTEGRITY-SHA256` attribute.
+///
+/// See [RFC 8489 -- 14.6. MESSAGE-INTEGRITY-SHA256] about this attribute.
+///
+/// [RFC 8489 -- 14.6. MESSAGE-INTEGRITY-SHA256]: https://tools.ietf.org/html/rfc8489#section-14.6
+///
+/// As with [`MessageIntegrity`], decoded instances do not hold any copy of the message and
+/// the `check_*` methods verify the HMAC value against the bytes of the received message.
+/// The given bytes must contain this attribute at the position where it was decoded;
+/// otherwise the `check_*` methods return an `Unauthorized` error.
+///
+/// [`MessageIntegrity`]: crate::rfc5389::attributes::MessageIntegrity
+#[derive(Debug, Clone)]
+pub struct MessageIntegritySha256 {
+    hmac_sha256: Vec<u8>,
+    offset: Option<usize>,
+}
+impl MessageIntegritySha256 {
+    /// The codepoint of the type of the attribute.
+    pub const CODEPOINT: u16 = 0x001C;
+
+    /// The minimum length of a (truncated) HMAC-SHA256 value.
+    pub const MIN_HMAC_LEN: usize = 16;
+
+    /// The maximum length of a HMAC-SHA256 value (i.e., the length of an untruncated one).
+    pub const MAX_HMAC_LEN: usize = 32;
+
+    /// utility function for creating HMAC-SHA256 signatures
+    pub(crate) fn generate_hmac_token(
+        key: &[u8],
+        message_bytes: &[u8],
+        offset: usize,
+        hmac_len: usize,
+    ) -> Result<[u8; 32]> {
+        let mut hasher: Hmac<Sha256> =
+            Mac::new_from_slice(key).expect("HMAC algoritms can take keys of any size");
+        track!(update_mac_with_preceding_bytes(
+            &mut hasher,
+            message_bytes,
+            offset,
+            hmac_len
+        ))?;
+        Ok(hasher.finalize().into_bytes().into())
+    }
+
+    /// Makes a new `MessageIntegritySha256` instance for short-term credentials.
+    pub fn new_short_term_credential<A>(message: &Message<A>, password: &str) -> Result<Self>
+    where
+        A: Attribute,
+    {
+        track!(Self::new_with_key(
+            message,
+            password.as_bytes(),
+            Self::MAX_HMAC_LEN
+        ))
+    }
+
+    /// Makes a new `MessageIntegritySha256` instance for long-term credentials.
+    ///
+    /// The key is derived by using `algorithm` (i.e., the password algorithm negotiated by
+    /// the `PASSWORD-ALGORITHM` attribute, or [`Algorithm::Md5`] if no algorithm was negotiated).
+    /// See [`Algorithm::long_term_key`] for the details.
+    ///
+    /// # Errors
+    ///
+    /// If the key cannot be derived, this will return an `ErrorKind::InvalidInput` error.
+    pub fn new_long_term_credential<A>(
+        message: &Message<A>,
+        algorithm: Algorithm,
+        username: &Username,
+        realm: &Realm,
+        password: &str,
+    ) -> Result<Self>
+    where
+        A: Attribute,
+    {
+        let key = track!(algorithm.long_term_key(username, realm, password))?;
+        track!(Self::new_with_key(message, &key[..], Self::MAX_HMAC_LEN))
+    }
+
+    /// Makes a new `MessageIntegritySha256` instance from the given key.
+    ///
+    /// The resulting HMAC-SHA256 value is truncated to the first `hmac_len` bytes.
+    ///
+    /// # Errors
+    ///
+    /// If `hmac_len` is not a multiple of `4` in the range of `16..=32`,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    pub fn new_with_key<A>(message: &Message<A>, key: &[u8], hmac_len: usize) -> Result<Self>
+    where
+        A: Attribute,
+    {
+        track!(Self::check_hmac_len(hmac_len))?;
+        let bytes = track!(MessageEncoder::default().encode_into_bytes(message.clone()))?;
+        let hmac = track!(Self::generate_hmac_token(
+            key,
+            &bytes,
+            bytes.len(),
+            hmac_len
+        ))?;
+        Ok(MessageIntegritySha256 {
+            hmac_sha256: hmac[..hmac_len].to_vec(),
+            offset: Some(bytes.len()),
+        })
+    }
+
+    /// Checks whether this has the valid short-term credential for `password`.
+    ///
+    /// `message_bytes` must be the bytes of the received message that contains this attribute.
+    pub fn check_short_term_credential(
+        &self,
+        message_bytes: &[u8],
+        password: &str,
+    ) -> std::result::Result<(), ErrorCode> {
+        self.check_with_key(message_bytes, password.as_bytes())
+    }
+
+    /// Checks whether this has the valid long-term credential for `password`.
+    ///
+    /// The key is derived by using `algorithm` in the same way as
+    /// [`MessageIntegritySha256::new_long_term_credential`].
+    /// If the key cannot be derived, this will return an `Unauthorized` error.
+    ///
+    /// `message_bytes` must be the bytes of the received message that contains this attribute.
+    pub fn check_long_term_credential(
+        &self,
+        message_bytes: &[u8],
+        algorithm: Algorithm,
+        username: &Username,
+        realm: &Realm,
+        password: &str,
+    ) -> std::result::Result<(), ErrorCode> {
+        let key = algorithm
+            .long_term_key(username, realm, password)
+            .map_err(|_| ErrorCode::from(errors::Unauthorized))?;
+        self.check_with_key(message_bytes, &key[..])
+    }
+
+    /// Checks whether this has the valid HMAC-SHA256 value for the long-term credential
+    /// identified by `userhash` (i.e., the `USERHASH` attribute is used instead of `USERNAME`).
+    ///
+    /// `username` is the one that the server found for `userhash`
+    /// (e.g., by using [`Userhash::lookup`]).
+    /// If `userhash` does not match `username` and `realm`, this will return
+    /// an `Unauthorized` error.
+    pub fn check_long_term_credential_with_userhash(
+        &self,
+        message_bytes: &[u8],
+        algorithm: Algorithm,
+        userhash: &Userhash,
+        username: &Username,
+        realm: &Realm,
+        password: &str,
+    ) -> std::result::Result<(), ErrorCode> {
+        if !userhash.matches(username, realm) {
+            return Err(errors::Unauthorized.into());
+        }
+        self.check_long_term_credential(message_bytes, algorithm, username, realm, password)
+    }
+
+    /// Checks whether this has the valid HMAC-SHA256 value for `key`.
+    ///
+    /// If the value is truncated, only the leading bytes of the expected value are compared.
+    /// The values are compared in constant time.
+    ///
+    /// `message_bytes` must be the bytes of the received message that contains this attribute
+    /// (see [`MessageIntegrity::check_with_key`]).
+    ///
+    /// [`MessageIntegrity::check_with_key`]: crate::rfc5389::attributes::MessageIntegrity::check_with_key
+    pub fn check_with_key(
+        &self,
+        message_bytes: &[u8],
+        key: &[u8],
+    ) -> std::result::Result<(), ErrorCode> {
+        let offset = self.offset.ok_or(errors::Unauthorized)?;
+        check_attribute_at(message_bytes, offset, Self::CODEPOINT, &self.hmac_sha256)
+            .map_err(|_| ErrorCode::from(errors::Unauthorized))?;
+        let expected =
+            Self::generate_hmac_token(key, message_bytes, offset, self.hmac_sha256.len())
+                .map_err(|_| ErrorCode::from(errors::Unauthorized))?;
+        let len = self.hmac_sha256.len();
+        if bool::from(self.hmac_sha256[..].ct_eq(&expected[..len])) {
+            Ok(())
+        } else {
+            Err(errors::Unauthorized.into())
+        }
+    }
+
+    /// Returns the (possibly truncated) HMAC-SHA256 of this instance.
+    pub fn hmac_sha256(&self) -> &[u8] {
+        &self.hmac_sha256
+    }
+
+    pub(crate) fn check_hmac_len(hmac_len: usize) -> Result<()> {
+        track_assert!(
+            (Self::MIN_HMAC_LEN..=Self::MAX_HMAC_LEN).contains(&hmac_len),
+            ErrorKind::InvalidInput;
+            hmac_len
+        );
+        track_assert_eq!(hmac_len % 4, 0, ErrorKind::InvalidInput; hmac_len);
+        Ok(())
+    }
+}
+impl PartialEq for MessageIntegritySha256 {
+    fn eq(&self, other: &Self) -> bool {
+        // The decoding position (`offset`) is not a part of the value
+        bool::from(self.hmac_sha256[..].ct_eq(&other.hmac_sha256[..]))
+    }
+}
+impl Eq for MessageIntegritySha256 {}
+impl Hash for MessageIntegritySha256 {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hmac_sha256.hash(state);
+    }
+}
+impl Attribute for MessageIntegritySha256 {
+    type Decoder = MessageIntegritySha256Decoder;
+    type Encoder = MessageIntegritySha256Encoder;
+
+    fn get_type(&self) -> AttributeType {
+        AttributeType::new(Self::CODEPOINT)
+    }
+
+    fn after_decode<A: Attribute>(&mut self, message: &Message<A>) -> Result<()> {
+        self.offset = message.attribute_offset();
+        Ok(())
+    }
+}
+
+/// [`MessageIntegritySha256`] decoder.
+#[derive(Debug, Default)]
+pub struct MessageIntegritySha256Decoder(RemainingBytesDecoder);
+impl MessageIntegritySha256Decoder {
+    /// Makes a new `MessageIntegritySha256Decoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_decode!(
+    MessageIntegritySha256Decoder,
+    MessageIntegritySha256,
+    |hmac_sha256: Vec<u8>| {
+        track!(MessageIntegritySha256::check_hmac_len(hmac_sha256.len()))?;
+        Ok(MessageIntegritySha256 {
+            hmac_sha256,
+            offset: None, // dummy
+        })
+    }
+);
+
+/// [`MessageIntegritySha256`] encoder.
+#[derive(Debug, Default)]
+pub struct MessageIntegritySha256Encoder(BytesEncoder);
+impl MessageIntegritySha256Encoder {
+    /// Makes a new `MessageIntegritySha256Encoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_encode!(
+    MessageIntegritySha256Encoder,
+    MessageIntegritySha256,
+    |item: Self::Item| item.hmac_sha256
+);
+
+/// Password algorithm.
+///
+/// See [RFC 8489 -- 18.5. Password Algorithms Registry] about the algorithms.
+///
+/// [RFC 8489 -- 18.5. Password Algorithms Registry]: https://tools.ietf.org/html/rfc8489#section-18.5
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    /// MD5 (`0x0001`).
+    Md5,
+
+    /// SHA-256 (`0x0002`).
+    Sha256,
+
+    /// Algorithm which is not supported by this crate.
+    Unknown(u16),
+}
+impl Algorithm {
+    /// Makes a new `Algorithm` instance from the given codepoint.
+    pub fn from_u16(codepoint: u16) -> Self {
+        match codepoint {
+            0x0001 => Algorithm::Md5,
+            0x0002 => Algorithm::Sha256,
+            _ => Algorithm::Unknown(codepoint),
+        }
+    }
+
+    /// Returns the codepoint corresponding this algorithm.
+    pub fn as_u16(self) -> u16 {
+        match self {
+            Algorithm::Md5 => 0x0001,
+            Algorithm::Sha256 => 0x0002,
+            Algorithm::Unknown(codepoint) => codepoint,
+        }
+    }
+
+    /// Derives the key for long-term credentials by using this algorithm.
+    ///
+    /// > key = MD5(username ":" OpaqueString(realm) ":" OpaqueString(password))
+    /// >
+    /// > key = SHA-256(username ":" OpaqueString(realm) ":" OpaqueString(password))
+    /// >
+    /// > [RFC 8489 -- 9.2.2. HMAC Key]
+    ///
+    /// Only `password` is processed by the OpaqueString profile here.
+    /// `username` and `realm` are used as they are, so they should be normalized when they are made
+    /// (e.g., by using [`Username::new_opaque_string`] and [`Realm::new_opaque_string`]).
+    ///
+    /// # Errors
+    ///
+    /// If the algorithm is `Algorithm::Unknown` or `password` is rejected by
+    /// the OpaqueString profile, this will return an `ErrorKind::InvalidInput` error.
+    ///
+    /// Although the OpaqueString profile rejects empty strings,
+    /// an empty `password` is accepted and used as it is.
+    ///
+    /// The returned key is zeroized when it is dropped.
+    ///
+    /// [RFC 8489 -- 9.2.2. HMAC Key]: https://tools.ietf.org/html/rfc8489#section-9.2.2
+    pub fn long_term_key(
+        self,
+        username: &Username,
+        realm: &Realm,
+        password: &str,
+    ) -> Result<Zeroizing<Vec<u8>>> {
+        let password = if password.is_empty() {
+            Zeroizing::new(String::new())
+        } else {
+            Zeroizing::new(track!(opaque_string(password))?.into_owned())
+        };
+        let input = Zeroizing::new(format!(
+            "{}:{}:{}",
+            username.name(),
+            realm.text(),
+            *password
+        ));
+        match self {
+            Algorithm::Md5 => Ok(Zeroizing::new(md5::compute(input.as_bytes()).0.to_vec())),
+            Algorithm::Sha256 => Ok(Zeroizing::new(Sha256::digest(input.as_bytes()).to_vec())),
+            Algorithm::Unknown(codepoint) => track_panic!(
+                ErrorKind::InvalidInput,
+                "Unsupported password algorithm: 0x{:04x}",
+                codepoint
+            ),
+        }
+    }
+}
+
+/// `PASSWORD-ALGORITHM` attribute.
+///
+/// See [RFC 8489 -- 14.12. PASSWORD-ALGORITHM] about this attribute.
+///
+/// [RFC 8489 -- 14.12. PASSWORD-ALGORITHM]: https://tools.ietf.org/html/rfc8489#section-14.12
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PasswordAlgorithm {
+    algorithm: Algorithm,
+    parameters: Vec<u8>,
+}
+impl PasswordAlgorithm {
+    /// The codepoint of the type of the attribute.
+    pub const CODEPOINT: u16 = 0x001D;
+
+    /// Makes a new `PasswordAlgorithm` instance without parameters.
+    pub fn new(algorithm: Algorithm) -> Self {
+        PasswordAlgorithm {
+            algorithm,
+            parameters: Vec::new(),
+        }
+    }
+
+    /// Makes a new `PasswordAlgorithm` instance with the given parameters.
+    ///
+    /// # Errors
+    ///
+    /// If the length of `parameters` is greater than `0xFFFF`,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    pub fn with_parameters(algorithm: Algorithm, parameters: Vec<u8>) -> Result<Self> {
+        track_assert!(parameters.len() <= 0xFFFF, ErrorKind::InvalidInput);
+        Ok(PasswordAlgorithm {
+            algorithm,
+            parameters,
+        })
+    }
+
+    /// Returns the algorithm of this instance.
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Returns the parameters of the algorithm.
+    pub fn parameters(&self) -> &[u8] {
+        &self.parameters
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.algorithm.as_u16().to_be_bytes());
+        buf.extend_from_slice(&(self.parameters.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&self.parameters);
+        buf.extend_from_slice(&[0; 3][..(4 - self.parameters.len() % 4) % 4]);
+    }
+
+    fn decode_from(bytes: &[u8]) -> Result<(Self, usize)> {
+        track_assert!(bytes.len() >= 4, ErrorKind::InvalidInput; bytes.len());
+        let algorithm = Algorithm::from_u16(BigEndian::read_u16(&bytes[0..2]));
+        let parameters_len = BigEndian::read_u16(&bytes[2..4]) as usize;
+        track_assert!(bytes.len() >= 4 + parameters_len, ErrorKind::InvalidInput;
+                      bytes.len(), parameters_len);
+        let parameters = bytes[4..][..parameters_len].to_vec();
+        let padding_len = (4 - parameters_len % 4) % 4;
+        let size = std::cmp::min(bytes.len(), 4 + parameters_len + padding_len);
+        Ok((
+            PasswordAlgorithm {
+                algorithm,
+                parameters,
+            },
+            size,
+        ))
+    }
+}
+impl Attribute for PasswordAlgorithm {
+    type Decoder = PasswordAlgorithmDecoder;
+    type Encoder = PasswordAlgorithmEncoder;
+
+    fn get_type(&self) -> AttributeType {
+        AttributeType::new(Self::CODEPOINT)
+    }
+}
+
+/// [`PasswordAlgorithm`] decoder.
+#[derive(Debug, Default)]
+pub struct PasswordAlgorithmDecoder(RemainingBytesDecoder);
+impl PasswordAlgorithmDecoder {
+    /// Makes a new `PasswordAlgorithmDecoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_decode!(PasswordAlgorithmDecoder, PasswordAlgorithm, |bytes: Vec<
+    u8,
+>| {
+    let (algorithm, size) = track!(PasswordAlgorithm::decode_from(&bytes))?;
+    track_assert_eq!(size, bytes.len(), ErrorKind::InvalidInput);
+    Ok(algorithm)
+});
+
+/// [`PasswordAlgorithm`] encoder.
+#[derive(Debug, Default)]
+pub struct PasswordAlgorithmEncoder(BytesEncoder);
+impl PasswordAlgorithmEncoder {
+    /// Makes a new `PasswordAlgorithmEncoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_encode!(
+    PasswordAlgorithmEncoder,
+    PasswordAlgorithm,
+    |item: Self::Item| {
+        let mut bytes = Vec::new();
+        item.encode_into(&mut bytes);
+        bytes
+    }
+);
+
+/// `PASSWORD-ALGORITHMS` attribute.
+///
+/// See [RFC 8489 -- 14.11. PASSWORD-ALGORITHMS] about this attribute.
+///
+/// [RFC 8489 -- 14.11. PASSWORD-ALGORITHMS]: https://tools.ietf.org/html/rfc8489#section-14.11
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PasswordAlgorithms {
+    algorithms: Vec<PasswordAlgorithm>,
+}
+impl PasswordAlgorithms {
+    /// The codepoint of the type of the attribute.
+    pub const CODEPOINT: u16 = 0x8002;
+
+    /// Makes a new `PasswordAlgorithms` instance.
+    pub fn new(algorithms: Vec<PasswordAlgorithm>) -> Self {
+        PasswordAlgorithms { algorithms }
+    }
+
+    /// Returns the algorithms listed in the attribute (in order of preference).
+    pub fn algorithms(&self) -> &[PasswordAlgorithm] {
+        &self.algorithms
+    }
+}
+impl Attribute for PasswordAlgorithms {
+    type Decoder = PasswordAlgorithmsDecoder;
+    type Encoder = PasswordAlgorithmsEncoder;
+
+    fn get_type(&self) -> AttributeType {
+        AttributeType::new(Self::CODEPOINT)
+    }
+}
+
+/// [`PasswordAlgorithms`] decoder.
+#[derive(Debug, Default)]
+pub struct PasswordAlgorithmsDecoder(RemainingBytesDecoder);
+impl PasswordAlgorithmsDecoder {
+    /// Makes a new `PasswordAlgorithmsDecoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_decode!(
+    PasswordAlgorithmsDecoder,
+    PasswordAlgorithms,
+    |bytes: Vec<u8>| {
+        let mut algorithms = Vec::new();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let (algorithm, size) = track!(PasswordAlgorithm::decode_from(&bytes[offset..]))?;
+            algorithms.push(algorithm);
+            offset += size;
+        }
+        Ok(PasswordAlgorithms { algorithms })
+    }
+);
+
+/// [`PasswordAlgorithms`] encoder.
+#[derive(Debug, Default)]
+pub struct PasswordAlgorithmsEncoder(BytesEncoder);
+impl PasswordAlgorithmsEncoder {
+    /// Makes a new `PasswordAlgorithmsEncoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_encode!(
+    PasswordAlgorithmsEncoder,
+    PasswordAlgorithms,
+    |item: Self::Item| {
+        let mut bytes = Vec::new();
+        for algorithm in &item.algorithms {
+            algorithm.encode_into(&mut bytes);
+        }
+        bytes
+    }
+);
+
+/// `USERHASH` attribute.
+///
+/// See [RFC 8489 -- 14.4. USERHASH] about this attribute.
+///
+/// [RFC 8489 -- 14.4. USERHASH]: https://tools.ietf.org/html/rfc8489#section-14.4
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Userhash([u8; 32]);
+impl Userhash {
+    /// The codepoint of the type of the attribute.
+    pub const CODEPOINT: u16 = 0x001E;
+
+    /// Makes a new `Userhash` instance.
+    ///
+    /// > userhash = SHA-256(OpaqueString(username) ":" OpaqueString(realm))
+    /// >
+    /// > [RFC 8489 -- 14.4. USERHASH]
+    ///
+    /// # Errors
+    ///
+    /// If `username` or `realm` is rejected by the OpaqueString profile,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    ///
+    /// [RFC 8489 -- 14.4. USERHASH]: https://tools.ietf.org/html/rfc8489#section-14.4
+    pub fn new(username: &Username, realm: &Realm) -> Result<Self> {
+        let username = track!(opaque_string(username.name()))?;
+        let realm = track!(opaque_string(realm.text()))?;
+        let hash = Sha256::digest(format!("{username}:{realm}").as_bytes());
+        Ok(Userhash(hash.into()))
+    }
+
+    /// Makes a new `Userhash` instance from the given hash value.
+    pub fn from_bytes(hash: [u8; 32]) -> Self {
+        Userhash(hash)
+    }
+
+    /// Returns the hash value of this instance.
+    pub fn hash(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Returns `true` if this hash is the one of the given username and realm.
+    ///
+    /// The hashes are compared in constant time.
+    pub fn matches(&self, username: &Username, realm: &Realm) -> bool {
+        Self::new(username, realm).is_ok_and(|h| bool::from(h.0.ct_eq(&self.0)))
+    }
+
+    /// Looks up the username corresponding to this hash from `usernames`.
+    ///
+    /// This computes and compares the hash of every candidate, even after a match is found,
+    /// so that the time taken does not reveal the position of the matching user.
+    /// If several candidates match, the first one is returned.
+    ///
+    /// If the number of users is large, it is recommended to precompute
+    /// a `HashMap<Userhash, _>` instead.
+    pub fn lookup<'a, I>(&self, realm: &Realm, usernames: I) -> Option<&'a Username>
+    where
+        I: IntoIterator<Item = &'a Username>,
+    {
+        usernames.into_iter().fold(None, |found, u| {
+            let matched = self.matches(u, realm);
+            found.or(if matched { Some(u) } else { None })
+        })
+    }
+}
+impl Attribute for Userhash {
+    type Decoder = UserhashDecoder;
+    type Encoder = UserhashEncoder;
+
+    fn get_type(&self) -> AttributeType {
+        AttributeType::new(Self::CODEPOINT)
+    }
+}
+
+/// [`Userhash`] decoder.
+#[derive(Debug, Default)]
+pub struct UserhashDecoder(CopyableBytesDecoder<[u8; 32]>);
+impl UserhashDecoder {
+    /// Makes a new `UserhashDecoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_decode!(UserhashDecoder, Userhash, |hash| Ok(Userhash(hash)));
+
+/// [`Userhash`] encoder.
+#[derive(Debug, Default)]
+pub struct UserhashEncoder(BytesEncoder<[u8; 32]>);
+impl UserhashEncoder {
+    /// Makes a new `UserhashEncoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_encode!(UserhashEncoder, Userhash, |item: Self::Item| item.0);
+
+/// `ALTERNATE-DOMAIN` attribute.
+///
+/// See [RFC 8489 -- 14.16. ALTERNATE-DOMAIN] about this attribute.
+///
+/// [RFC 8489 -- 14.16. ALTERNATE-DOMAIN]: https://tools.ietf.org/html/rfc8489#section-14.16
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AlternateDomain {
+    domain: String,
+}
+impl AlternateDomain {
+    /// The codepoint of the type of the attribute.
+    pub const CODEPOINT: u16 = 0x8003;
+
+    /// Makes a new `AlternateDomain` instance.
+    ///
+    /// # Errors
+    ///
+    /// `domain` must be an ASCII string (i.e., the A-label form of the domain name)
+    /// and its length must be no more than `255` bytes.
+    /// If it is not, this will return an `ErrorKind::InvalidInput` error.
+    pub fn new(domain: String) -> Result<Self> {
+        track_assert!(domain.is_ascii(), ErrorKind::InvalidInput; domain);
+        track_assert!(domain.len() <= 255, ErrorKind::InvalidInput; domain);
+        Ok(AlternateDomain { domain })
+    }
+
+    /// Returns the domain name of this instance.
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+}
+impl Attribute for AlternateDomain {
+    type Decoder = AlternateDomainDecoder;
+    type Encoder = AlternateDomainEncoder;
+
+    fn get_type(&self) -> AttributeType {
+        AttributeType::new(Self::CODEPOINT)
+    }
+}
+
+/// [`AlternateDomain`] decoder.
+#[derive(Debug, Default)]
+pub struct AlternateDomainDecoder(Utf8Decoder);
+impl AlternateDomainDecoder {
+    /// Makes a new `AlternateDomainDecoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_decode!(
+    AlternateDomainDecoder,
+    AlternateDomain,
+    AlternateDomain::new
+);
+
+/// [`AlternateDomain`] encoder.
+#[derive(Debug, Default)]
+pub struct AlternateDomainEncoder(Utf8Encoder);
+impl AlternateDomainEncoder {
+    /// Makes a new `AlternateDomainEncoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_encode!(
+    AlternateDomainEncoder,
+    AlternateDomain,
+    |item: Self::Item| item.domain
+);
+
+/// The prefix of the nonce that indicates the server supports the features introduced by [RFC 8489].
+///
+/// See [RFC 8489 -- 9.2. Long-Term Credential Mechanism] for details.
+///
+/// [RFC 8489]: https://tools.ietf.org/html/rfc8489
+/// [RFC 8489 -- 9.2. Long-Term Credential Mechanism]: https://tools.ietf.org/html/rfc8489#section-9.2
+pub const NONCE_COOKIE: &str = "obMatJos2";
+
+/// The "STUN Security Features" that are encoded in the nonce cookie.
+///
+/// See [RFC 8489 -- 9.2. Long-Term Credential Mechanism] and
+/// [RFC 8489 -- 18.1. STUN Security Features Registry] for details.
+///
+/// [RFC 8489 -- 9.2. Long-Term Credential Mechanism]: https://tools.ietf.org/html/rfc8489#section-9.2
+/// [RFC 8489 -- 18.1. STUN Security Features Registry]: https://tools.ietf.org/html/rfc8489#section-18.1
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SecurityFeatures(u32);
+impl SecurityFeatures {
+    /// "Password algorithms" feature (bit 0).
+    ///
+    /// Bits are numbered from the least significant one, as in the test vector of
+    /// [RFC 8489 -- B.1. Sample Request with Long-Term Authentication with MESSAGE-INTEGRITY-SHA256 and USERHASH].
+    ///
+    /// [RFC 8489 -- B.1. Sample Request with Long-Term Authentication with MESSAGE-INTEGRITY-SHA256 and USERHASH]: https://tools.ietf.org/html/rfc8489#appendix-B.1
+    pub const PASSWORD_ALGORITHMS: Self = SecurityFeatures(0x00_0001);
+
+    /// "Username anonymity" feature (bit 1).
+    pub const USERNAME_ANONYMITY: Self = SecurityFeatures(0x00_0002);
+
+    /// Makes a new `SecurityFeatures` instance from the 24-bit feature set.
+    ///
+    /// # Errors
+    ///
+    /// If `bits` exceeds `24` bits, this will return an `ErrorKind::InvalidInput` error.
+    pub fn from_bits(bits: u32) -> Result<Self> {
+        track_assert!(bits <= 0xFF_FFFF, ErrorKind::InvalidInput; bits);
+        Ok(SecurityFeatures(bits))
+    }
+
+    /// Returns the 24-bit feature set of this instance.
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Returns `true` if all the features in `other` are contained in this set.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns `true` if the "Password algorithms" feature is enabled.
+    ///
+    /// If a client receives a nonce with this feature, it must use `PASSWORD-ALGORITHMS` and
+    /// `PASSWORD-ALGORITHM` attributes (otherwise it may be subject to a bid-down attack).
+    pub fn password_algorithms(self) -> bool {
+        self.contains(Self::PASSWORD_ALGORITHMS)
+    }
+
+    /// Returns `true` if the "Username anonymity" feature is enabled.
+    pub fn username_anonymity(self) -> bool {
+        self.contains(Self::USERNAME_ANONYMITY)
+    }
+
+    /// Encodes this feature set into the nonce cookie (i.e., `NONCE_COOKIE` + base64 encoded features).
+    pub fn to_nonce_cookie(self) -> String {
+        const BASE64: &[u8; 64] =
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut cookie = NONCE_COOKIE.to_owned();
+        for i in (0..4).rev() {
+            cookie.push(BASE64[(self.0 >> (i * 6)) as usize & 0x3F] as char);
+        }
+        cookie
+    }
+
+    /// Decodes the feature set from the nonce cookie at the beginning of `nonce`.
+    ///
+    /// If `nonce` does not start with a valid nonce cookie, this will return `None`.
+    pub fn from_nonce(nonce: &str) -> Option<Self> {
+        let encoded = nonce.strip_prefix(NONCE_COOKIE)?.as_bytes().get(..4)?;
+        let mut bits = 0;
+        for &c in encoded {
+            let v = match c {
+                b'A'..=b'Z' => c - b'A',
+                b'a'..=b'z' => c - b'a' + 26,
+                b'0'..=b'9' => c - b'0' + 52,
+                b'+' => 62,
+                b'/' => 63,
+                _ => return None,
+            };
+            bits = (bits << 6) | u32::from(v);
+        }
+        Some(SecurityFeatures(bits))
+    }
+}
+impl std::ops::BitOr for SecurityFeatures {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        SecurityFeatures(self.0 | rhs.0)
+    }
+}
+
+pub(crate) fn opaque_string(s: &str) -> Result<Cow<'_, str>> {
+    OpaqueString::enforce(s).map_err(|e| {
+        ErrorKind::InvalidInput
+            .cause(format!("OpaqueString: {e}"))
+            .into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rfc5389::attributes::{MessageIntegrity, Nonce};
+    use crate::rfc5389::methods::BINDING;
+    use crate::rfc8489::Attribute as Rfc8489Attribute;
+    use crate::{MessageClass, MessageDecoder, TransactionId};
+    use bytecodec::DecodeExt;
+    use trackable::result::TestResult;
+
+    fn encode_and_decode(
+        message: Message<Rfc8489Attribute>,
+    ) -> Result<(Vec<u8>, Message<Rfc8489Attribute>)> {
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+        let decoded = track!(MessageDecoder::<Rfc8489Attribute>::new().decode_from_bytes(&bytes))?;
+        Ok((bytes, track!(decoded.map_err(bytecodec::Error::from))?))
+    }
+
+    #[test]
+    fn message_integrity_sha256_short_term_credential_works() -> TestResult {
+        let mut message = Message::new(MessageClass::Request, BINDING, TransactionId::new([3; 12]));
+        let attr = track!(MessageIntegritySha256::new_short_term_credential(
+            &message, "foo"
+        ))?;
+        assert_eq!(attr.hmac_sha256().len(), 32);
+        message.add_attribute(attr);
+
+        let (bytes, decoded) = track!(encode_and_decode(message))?;
+        let attr = decoded
+            .get_attribute::<MessageIntegritySha256>()
+            .expect("never fails");
+        assert!(attr.check_short_term_credential(&bytes, "foo").is_ok());
+        assert!(attr.check_short_term_credential(&bytes, "bar").is_err());
+
+        // Bytes that do not have the attribute at the decoded position
+        assert!(attr
+            .check_short_term_credential(&bytes[..40], "foo")
+            .is_err());
+        let mut other = bytes.clone();
+        other[20..22].copy_from_slice(&MessageIntegrity::CODEPOINT.to_be_bytes());
+        assert!(attr.check_short_term_credential(&other, "foo").is_err());
+        let mut other = bytes.clone();
+        other[30] ^= 1;
+        assert!(attr.check_short_term_credential(&other, "foo").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn message_integrity_sha256_long_term_credential_works() -> TestResult {
+        let username = track!(Username::new("foo".to_owned()))?;
+        let realm = track!(Realm::new("example.org".to_owned()))?;
+
+        let mut message = Message::new(MessageClass::Request, BINDING, TransactionId::new([3; 12]));
+        message.add_attribute(track!(MessageIntegritySha256::new_long_term_credential(
+            &message,
+            Algorithm::Sha256,
+            &username,
+            &realm,
+            "bar"
+        ))?);
+
+        let (bytes, decoded) = track!(encode_and_decode(message))?;
+        let attr = decoded
+            .get_attribute::<MessageIntegritySha256>()
+            .expect("never fails");
+        let key = Sha256::digest(b"foo:example.org:bar");
+        assert!(attr.check_with_key(&bytes, &key).is_ok());
+        assert!(attr
+            .check_long_term_credential(&bytes, Algorithm::Sha256, &username, &realm, "bar")
+            .is_ok());
+        assert!(attr
+            .check_long_term_credential(&bytes, Algorithm::Sha256, &username, &realm, "baz")
+            .is_err());
+
+        // A key derived by another algorithm does not match
+        assert!(attr
+            .check_long_term_credential(&bytes, Algorithm::Md5, &username, &realm, "bar")
+            .is_err());
+        assert!(attr
+            .check_long_term_credential(&bytes, Algorithm::Unknown(3), &username, &realm, "bar")
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn message_integrity_sha256_truncation_works() -> TestResult {
+        let mut message = Message::new(MessageClass::Request, BINDING, TransactionId::new([3; 12]));
+        let attr = track!(MessageIntegritySha256::new_with_key(&message, b"foo", 16))?;
+        assert_eq!(attr.hmac_sha256().len(), 16);
+        message.add_attribute(attr);
+
+        let (bytes, decoded) = track!(encode_and_decode(message))?;
+        let attr = decoded
+            .get_attribute::<MessageIntegritySha256>()
+            .expect("never fails");
+        assert_eq!(attr.hmac_sha256().len(), 16);
+        assert!(attr.check_with_key(&bytes, b"foo").is_ok());
+        assert!(attr.check_with_key(&bytes, b"bar").is_err());
+
+        assert!(MessageIntegritySha256::new_with_key(&decoded, b"foo", 12).is_err());
+        assert!(MessageIntegritySha256::new_with_key(&decoded, b"foo", 18).is_err());
+        assert!(MessageIntegritySha256::new_with_key(&decoded, b"foo", 36).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn password_algorithm_encoder_works() -> TestResult {
+        let mut encoder = PasswordAlgorithmEncoder::new();
+        let bytes = track!(encoder.encode_into_bytes(PasswordAlgorithm::new(Algorithm::Sha256)))?;
+        assert_eq!(bytes, [0, 2, 0, 0]);
+
+        let algorithm = track!(PasswordAlgorithm::with_parameters(
+            Algorithm::Unknown(0xFF),
+            vec![1, 2]
+        ))?;
+        let bytes = track!(encoder.encode_into_bytes(algorithm))?;
+        assert_eq!(bytes, [0, 0xFF, 0, 2, 1, 2, 0, 0]);
+        Ok(())
+    }
+
+    #[test]
+    fn password_algorithms_decoder_works() -> TestResult {
+        let mut decoder = PasswordAlgorithmsDecoder::new();
+        let bytes = [0, 0xFF, 0, 2, 1, 2, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0];
+        let item = track!(decoder.decode_from_bytes(&bytes))?;
+        assert_eq!(
+            item.algorithms(),
+            [
+                track!(PasswordAlgorithm::with_parameters(
+                    Algorithm::Unknown(0xFF),
+                    vec![1, 2]
+                ))?,
+                PasswordAlgorithm::new(Algorithm::Sha256),
+                PasswordAlgorithm::new(Algorithm::Md5),
+            ]
+        );
+
+        let mut encoder = PasswordAlgorithmsEncoder::new();
+        assert_eq!(track!(encoder.encode_into_bytes(item))?, bytes);
+
+        assert!(decoder.decode_from_bytes(&[0, 1, 0, 4, 0, 0]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn long_term_key_works() -> TestResult {
+        let username = track!(Username::new("user".to_owned()))?;
+        let realm = track!(Realm::new("realm".to_owned()))?;
+
+        let key = track!(Algorithm::Md5.long_term_key(&username, &realm, "pass"))?;
+        assert_eq!(key[..], md5::compute(b"user:realm:pass").0);
+
+        let key = track!(Algorithm::Sha256.long_term_key(&username, &realm, "pass"))?;
+        assert_eq!(key[..], Sha256::digest(b"user:realm:pass")[..]);
+
+        assert!(Algorithm::Unknown(0xFF)
+            .long_term_key(&username, &realm, "pass")
+            .is_err());
+
+        let mut message = Message::new(MessageClass::Request, BINDING, TransactionId::new([3; 12]));
+        message.add_attribute(PasswordAlgorithm::new(Algorithm::Sha256));
+        message.add_attribute(track!(MessageIntegritySha256::new_with_key(
+            &message, &key, 32
+        ))?);
+
+        let (bytes, decoded) = track!(encode_and_decode(message))?;
+        let algorithm = decoded
+            .get_attribute::<PasswordAlgorithm>()
+            .expect("never fails")
+            .algorithm();
+        let key = track!(algorithm.long_term_key(&username, &realm, "pass"))?;
+        let attr = decoded
+            .get_attribute::<MessageIntegritySha256>()
+            .expect("never fails");
+        assert!(attr.check_with_key(&bytes, &key).is_ok());
+        assert!(attr
+            .check_long_term_credential(&bytes, algorithm, &username, &realm, "pass")
+            .is_ok());
+        assert!(attr
+            .check_long_term_credential(&bytes, Algorithm::Md5, &username, &realm, "pass")
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn userhash_works() -> TestResult {
+        let username = track!(Username::new("user".to_owned()))?;
+        let realm = track!(Realm::new("realm".to_owned()))?;
+        let userhash = track!(Userhash::new(&username, &realm))?;
+        assert_eq!(&userhash.hash()[..], &Sha256::digest(b"user:realm")[..]);
+
+        // OpaqueString maps non-ASCII spaces to ASCII space.
+        let spaced = track!(Username::new("us\u{3000}er".to_owned()))?;
+        let expected = Sha256::digest(b"us er:realm");
+        assert_eq!(
+            &track!(Userhash::new(&spaced, &realm))?.hash()[..],
+            &expected[..]
+        );
+
+        let users = vec![track!(Username::new("alice".to_owned()))?, username.clone()];
+        assert_eq!(userhash.lookup(&realm, &users), Some(&username));
+        assert_eq!(userhash.lookup(&realm, &users[..1]), None);
+
+        // Every candidate is checked even after a match is found.
+        let duplicated = [username.clone(), username.clone(), users[0].clone()];
+        let mut visited = 0;
+        let found = userhash.lookup(&realm, duplicated.iter().inspect(|_| visited += 1));
+        assert!(std::ptr::eq(found.expect("never fails"), &duplicated[0]));
+        assert_eq!(visited, 3);
+
+        let mut message = Message::new(MessageClass::Request, BINDING, TransactionId::new([3; 12]));
+        message.add_attribute(userhash.clone());
+        message.add_attribute(track!(MessageIntegritySha256::new_long_term_credential(
+            &message,
+            Algorithm::Md5,
+            &username,
+            &realm,
+            "pass"
+        ))?);
+
+        let (bytes, decoded) = track!(encode_and_decode(message))?;
+        let userhash = decoded.get_attribute::<Userhash>().expect("never fails");
+        let username = userhash.lookup(&realm, &users).expect("never fails");
+        let mi = decoded
+            .get_attribute::<MessageIntegritySha256>()
+            .expect("never fails");
+        assert!(mi
+            .check_long_term_credential_with_userhash(
+                &bytes,
+                Algorithm::Md5,
+                userhash,
+                username,
+                &realm,
+                "pass"
+            )
+            .is_ok());
+        assert!(mi
+            .check_long_term_credential_with_userhash(
+                &bytes,
+                Algorithm::Md5,
+                userhash,
+                &users[0],
+                &realm,
+                "pass"
+            )
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn nonce_cookie_works() -> TestResult {
+        let features = SecurityFeatures::PASSWORD_ALGORITHMS | SecurityFeatures::USERNAME_ANONYMITY;
+        assert_eq!(features.to_nonce_cookie(), "obMatJos2AAAD");
+
+        let nonce = track!(Nonce::with_security_features(
+            SecurityFeatures::PASSWORD_ALGORITHMS,
+            "f//499k954d6OL34oL9FSTvy64sA"
+        ))?;
+        assert_eq!(nonce.value(), "obMatJos2AAABf//499k954d6OL34oL9FSTvy64sA");
+
+        let features = nonce.security_features().expect("never fails");
+        assert!(features.password_algorithms());
+        assert!(!features.username_anonymity());
+        assert_eq!(features.bits(), 0x00_0001);
+
+        let nonce = track!(Nonce::new("f//499k954d6OL34oL9FSTvy64sA".to_owned()))?;
+        assert_eq!(nonce.security_features(), None);
+        assert_eq!(SecurityFeatures::from_nonce("obMatJos2AA"), None);
+        assert!(SecurityFeatures::from_bits(0x100_0000).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rfc8489_test_vector_nonce_cookie_works() {
+        // RFC 8489 -- B.1. (the request uses `USERHASH`)
+        let features = SecurityFeatures::from_nonce("obMatJos2AAACf//499k954d6OL34oL9FSTvy64sA")
+            .expect("never fails");
+        assert_eq!(features.bits(), 0x00_0002);
+        assert!(features.username_anonymity());
+        assert!(!features.password_algorithms());
+    }
+
+    #[test]
+    fn credential_normalization_works() -> TestResult {
+        let username = track!(Username::new_saslprep("user\u{00AD}"))?;
+        assert_eq!(username.name(), "user");
+        let realm = track!(Realm::new_saslprep("realm\u{2168}"))?;
+        assert_eq!(realm.text(), "realmIX");
+
+        let username = track!(Username::new_opaque_string("us\u{3000}er"))?;
+        assert_eq!(username.name(), "us er");
+        let realm = track!(Realm::new_opaque_string("rea\u{0301}lm"))?;
+        assert_eq!(realm.text(), "re\u{00E1}lm");
+        assert!(Username::new_opaque_string("").is_err());
+
+        // The password is processed by using OpaqueString (NFC) before hashing.
+        let key = track!(Algorithm::Sha256.long_term_key(&username, &realm, "pa\u{0301}ss"))?;
+        let expected = Sha256::digest("us er:re\u{00E1}lm:p\u{00E1}ss".as_bytes());
+        assert_eq!(key[..], expected[..]);
+        let key = track!(Algorithm::Sha256.long_term_key(&username, &realm, ""))?;
+        let expected = Sha256::digest("us er:re\u{00E1}lm:".as_bytes());
+        assert_eq!(key[..], expected[..]);
+
+        let mut message = Message::new(MessageClass::Request, BINDING, TransactionId::new([3; 12]));
+        message.add_attribute(track!(MessageIntegritySha256::new_long_term_credential(
+            &message,
+            Algorithm::Sha256,
+            &username,
+            &realm,
+            "pa\u{0301}ss"
+        ))?);
+        let (bytes, decoded) = track!(encode_and_decode(message))?;
+        let mi = decoded
+            .get_attribute::<MessageIntegritySha256>()
+            .expect("never fails");
+        assert!(mi
+            .check_long_term_credential(&bytes, Algorithm::Sha256, &username, &realm, "p\u{00E1}ss")
+            .is_ok());
+        assert!(mi
+            .check_long_term_credential(&bytes, Algorithm::Sha256, &username, &realm, "")
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+//! [RFC 8489(STUN)][RFC 8489] specific components.
+//!
+//! [RFC 8489]: https://tools.ietf.org/html/rfc8489
+use self::attributes::*;
+use crate::rfc5389::attributes::{AlternateServer, ErrorCode};
+use crate::rfc5389::errors::TryAlternate;
+use crate::{Message, MessageClass};
+use std::net::SocketAddr;
+
+pub mod attributes;
+
+define_attribute_enums!(
+    Attribute,
+    AttributeDecoder,
+    AttributeEncoder,
+    [
+        MessageIntegritySha256,
+        PasswordAlgorithm,
+        Userhash,
+        PasswordAlgorithms,
+        AlternateDomain
+    ]
+);
+
+/// Makes a "300 Try Alternate" error response to `request`.
+///
+/// The response has `ERROR-CODE`, `ALTERNATE-SERVER` and `ALTERNATE-DOMAIN` attributes.
+///
+/// > If the transport protocol uses TLS or DTLS, then the server MUST
+/// > include an ALTERNATE-DOMAIN attribute containing the name of the
+/// > alternate server.
+/// >
+/// > [RFC 8489 -- 10. ALTERNATE-SERVER Mechanism]
+///
+/// Note that if the request was authenticated, the caller should append
+/// `MESSAGE-INTEGRITY` (or `MESSAGE-INTEGRITY-SHA256`) attribute to the resulting message.
+///
+/// [RFC 8489 -- 10. ALTERNATE-SERVER Mechanism]: https://tools.ietf.org/html/rfc8489#section-10
+pub fn try_alternate_response<A, T>(
+    request: &Message<T>,
+    server: SocketAddr,
+    domain: AlternateDomain,
+) -> Message<A>
+where
+    A: crate::Attribute + From<ErrorCode> + From<AlternateServer> + From<AlternateDomain>,
+    T: crate::Attribute,
+{
+    let mut response = Message::new(
+        MessageClass::ErrorResponse,
+        request.method(),
+        request.transaction_id(),
+    );
+    response.add_attribute(ErrorCode::from(TryAlternate));
+    response.add_attribute(AlternateServer::new(server));
+    response.add_attribute(domain);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rfc5389::methods::BINDING;
+    use crate::test_util::TestAttribute;
+    use crate::{MessageDecoder, MessageEncoder, TransactionId};
+    use bytecodec::{DecodeExt, EncodeExt};
+    use trackable::result::TestResult;
+
+    #[test]
+    fn try_alternate_response_works() -> TestResult {
+        let request = Message::<TestAttribute>::new(
+            MessageClass::Request,
+            BINDING,
+            TransactionId::new([7; 12]),
+        );
+        let server = "192.0.2.1:5349".parse().unwrap();
+        let domain = track!(AlternateDomain::new("stun.example.com".to_owned()))?;
+        let response: Message<TestAttribute> = try_alternate_response(&request, server, domain);
+
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(response))?;
+        let decoded = track!(MessageDecoder::<TestAttribute>::new().decode_from_bytes(&bytes))?
+            .expect("never fails");
+        assert_eq!(decoded.class(), MessageClass::ErrorResponse);
+        assert_eq!(decoded.transaction_id(), request.transaction_id());
+        assert_eq!(
+            decoded.get_attribute::<ErrorCode>().map(|e| e.code()),
+            Some(TryAlternate::CODEPOINT)
+        );
+        assert_eq!(
+            decoded
+                .get_attribute::<AlternateServer>()
+                .map(|a| a.address()),
+            Some(server)
+        );
+        assert_eq!(
+            decoded
+                .get_attribute::<AlternateDomain>()
+                .map(|a| a.domain()),
+            Some("stun.example.com")
+        );
+        Ok(())
+    }
+}
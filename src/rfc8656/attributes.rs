@@ -2,11 +2,14 @@
 //!
 //! [RFC 8656]: https://tools.ietf.org/html/rfc8656
 
-use bytecodec::fixnum::{U32beDecoder, U32beEncoder};
-use bytecodec::{ByteCount, Decode, Encode, Eos, Result, SizedEncode, TryTaggedDecode};
+use bytecodec::bytes::{Utf8Decoder, Utf8Encoder};
+use bytecodec::fixnum::{U32beDecoder, U32beEncoder, U64beDecoder, U64beEncoder};
+use bytecodec::tuple::{TupleDecoder, TupleEncoder};
+use bytecodec::{ByteCount, Decode, Encode, Eos, ErrorKind, Result, SizedEncode, TryTaggedDecode};
 use std::fmt;
 
 use crate::attribute::{Attribute, AttributeType};
+use crate::rfc5389::attributes::ErrorCode;
 
 macro_rules! impl_decode {
     ($decoder:ty, $item:ident, $and_then:expr) => {
@@ -195,6 +198,183 @@ impl_encode!(
     |item: Self::Item| { item.0 }
 );
 
+/// This attribute is used by servers to signal the reason for not allocating the requested address family.
+///
+/// See <https://datatracker.ietf.org/doc/html/rfc8656#name-address-error-code> for details.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AddressErrorCode {
+    family: AddressFamily,
+    error: ErrorCode,
+}
+impl AddressErrorCode {
+    /// The codepoint of the type of the attribute.
+    pub const CODEPOINT: u16 = 0x8001;
+
+    /// Makes a new `AddressErrorCode` instance.
+    pub fn new(family: AddressFamily, error: ErrorCode) -> Self {
+        AddressErrorCode { family, error }
+    }
+
+    /// Returns the address family that the error applies to.
+    pub fn address_family(&self) -> AddressFamily {
+        self.family
+    }
+
+    /// Returns the error.
+    pub fn error(&self) -> &ErrorCode {
+        &self.error
+    }
+}
+impl Attribute for AddressErrorCode {
+    type Decoder = AddressErrorCodeDecoder;
+    type Encoder = AddressErrorCodeEncoder;
+
+    fn get_type(&self) -> AttributeType {
+        AttributeType::new(Self::CODEPOINT)
+    }
+}
+
+/// [`AddressErrorCode`] decoder.
+#[derive(Debug, Default)]
+pub struct AddressErrorCodeDecoder(TupleDecoder<(U32beDecoder, Utf8Decoder)>);
+impl AddressErrorCodeDecoder {
+    /// Makes a new `AddressErrorCodeDecoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_decode!(AddressErrorCodeDecoder, AddressErrorCode, |(
+    value,
+    reason_phrase,
+): (
+    u32,
+    String
+)| {
+    let family = track!(address_family_from_u8((value >> 24) as u8))?;
+    let class = (value >> 8) & 0b111;
+    let number = value & 0b1111_1111;
+    track_assert!(number < 100, ErrorKind::InvalidInput);
+
+    let error = track!(ErrorCode::new((class * 100 + number) as u16, reason_phrase))?;
+    Ok(AddressErrorCode { family, error })
+});
+
+/// [`AddressErrorCode`] encoder.
+#[derive(Debug, Default)]
+pub struct AddressErrorCodeEncoder(TupleEncoder<(U32beEncoder, Utf8Encoder)>);
+impl AddressErrorCodeEncoder {
+    /// Makes a new `AddressErrorCodeEncoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_encode!(
+    AddressErrorCodeEncoder,
+    AddressErrorCode,
+    |item: Self::Item| {
+        let family = u32::from(address_family_as_u8(item.family));
+        let class = u32::from(item.error.code() / 100);
+        let number = u32::from(item.error.code() % 100);
+        let value = (family << 24) | (class << 8) | number;
+        (value, item.error.reason_phrase().to_owned())
+    }
+);
+
+/// This attribute is used by servers to forward ICMP errors received from peers to clients.
+///
+/// See <https://datatracker.ietf.org/doc/html/rfc8656#name-icmp> for details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Icmp {
+    icmp_type: u8,
+    icmp_code: u8,
+    error_data: u32,
+}
+impl Icmp {
+    /// The codepoint of the type of the attribute.
+    pub const CODEPOINT: u16 = 0x8004;
+
+    /// Makes a new `Icmp` instance.
+    pub fn new(icmp_type: u8, icmp_code: u8, error_data: u32) -> Self {
+        Icmp {
+            icmp_type,
+            icmp_code,
+            error_data,
+        }
+    }
+
+    /// Returns the type of the ICMP message.
+    pub fn icmp_type(&self) -> u8 {
+        self.icmp_type
+    }
+
+    /// Returns the code of the ICMP message.
+    pub fn icmp_code(&self) -> u8 {
+        self.icmp_code
+    }
+
+    /// Returns the error data of the ICMP message (e.g., MTU for "Packet Too Big").
+    pub fn error_data(&self) -> u32 {
+        self.error_data
+    }
+}
+impl Attribute for Icmp {
+    type Decoder = IcmpDecoder;
+    type Encoder = IcmpEncoder;
+
+    fn get_type(&self) -> AttributeType {
+        AttributeType::new(Self::CODEPOINT)
+    }
+}
+
+/// [`Icmp`] decoder.
+#[derive(Debug, Default)]
+pub struct IcmpDecoder(U64beDecoder);
+impl IcmpDecoder {
+    /// Makes a new `IcmpDecoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_decode!(IcmpDecoder, Icmp, |value: u64| Ok(Icmp {
+    icmp_type: (value >> 40) as u8,
+    icmp_code: (value >> 32) as u8,
+    error_data: value as u32,
+}));
+
+/// [`Icmp`] encoder.
+#[derive(Debug, Default)]
+pub struct IcmpEncoder(U64beEncoder);
+impl IcmpEncoder {
+    /// Makes a new `IcmpEncoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl_encode!(IcmpEncoder, Icmp, |item: Self::Item| {
+    (u64::from(item.icmp_type) << 40)
+        | (u64::from(item.icmp_code) << 32)
+        | u64::from(item.error_data)
+});
+
+fn address_family_from_u8(family: u8) -> Result<AddressFamily> {
+    match family {
+        FAMILY_IPV4 => Ok(AddressFamily::V4),
+        FAMILY_IPV6 => Ok(AddressFamily::V6),
+        family => track_panic!(
+            bytecodec::ErrorKind::InvalidInput,
+            "Unknown address family: {}",
+            family
+        ),
+    }
+}
+
+fn address_family_as_u8(family: AddressFamily) -> u8 {
+    match family {
+        AddressFamily::V4 => FAMILY_IPV4,
+        AddressFamily::V6 => FAMILY_IPV6,
+    }
+}
+
 /// [`RequestedAddressFamily`] decoder.
 #[derive(Debug, Default)]
 pub struct AddressFamilyDecoder {
@@ -210,16 +390,7 @@ impl Decode for AddressFamilyDecoder {
 
     fn finish_decoding(&mut self) -> Result<Self::Item> {
         let [fam, _, _, _] = self.family.finish_decoding()?.to_be_bytes();
-
-        match fam {
-            FAMILY_IPV4 => Ok(AddressFamily::V4),
-            FAMILY_IPV6 => Ok(AddressFamily::V6),
-            family => track_panic!(
-                bytecodec::ErrorKind::InvalidInput,
-                "Unknown address family: {}",
-                family
-            ),
-        }
+        address_family_from_u8(fam)
     }
 
     fn requiring_bytes(&self) -> ByteCount {
@@ -245,12 +416,7 @@ impl Encode for AddressFamilyEncoder {
     }
 
     fn start_encoding(&mut self, item: Self::Item) -> Result<()> {
-        let fam_byte = match item {
-            AddressFamily::V4 => FAMILY_IPV4,
-            AddressFamily::V6 => FAMILY_IPV6,
-        };
-
-        let bytes = [fam_byte, 0, 0, 0];
+        let bytes = [address_family_as_u8(item), 0, 0, 0];
 
         self.family.start_encoding(u32::from_be_bytes(bytes))
     }
@@ -269,7 +435,12 @@ impl SizedEncode for AddressFamilyEncoder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rfc5766::methods::ALLOCATE;
+    use crate::rfc8656::errors::AddressFamilyNotSupported;
+    use crate::rfc8656::Attribute as Rfc8656Attribute;
+    use crate::{Message, MessageClass, MessageDecoder, MessageEncoder, TransactionId};
     use bytecodec::{DecodeExt, EncodeExt};
+    use trackable::result::TestResult;
 
     #[test]
     fn address_family_encoder_works() {
@@ -292,4 +463,48 @@ mod tests {
         let fam = decoder.decode_from_bytes(&[2, 0, 0, 0]).unwrap();
         assert_eq!(fam, AddressFamily::V6);
     }
+
+    #[test]
+    fn rfc8656_attributes_work() -> TestResult {
+        let mut message = Message::<Rfc8656Attribute>::new(
+            MessageClass::ErrorResponse,
+            ALLOCATE,
+            TransactionId::new([0; 12]),
+        );
+        message.add_attribute(RequestedAddressFamily::new(AddressFamily::V4));
+        message.add_attribute(AdditionalAddressFamily::new(AddressFamily::V6));
+        message.add_attribute(AddressErrorCode::new(
+            AddressFamily::V6,
+            AddressFamilyNotSupported.into(),
+        ));
+        message.add_attribute(Icmp::new(2, 0, 1280));
+
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+        assert_eq!(
+            &bytes[bytes.len() - 12..],
+            [0x80, 0x04, 0x00, 0x08, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x05, 0x00]
+        );
+
+        let decoded = track!(MessageDecoder::<Rfc8656Attribute>::new().decode_from_bytes(&bytes))?
+            .expect("never fails");
+        assert_eq!(
+            decoded
+                .get_attribute::<AdditionalAddressFamily>()
+                .map(|a| a.address_family()),
+            Some(AddressFamily::V6)
+        );
+        let address_error = decoded
+            .get_attribute::<AddressErrorCode>()
+            .expect("never fails");
+        assert_eq!(address_error.address_family(), AddressFamily::V6);
+        assert_eq!(
+            address_error.error().code(),
+            AddressFamilyNotSupported::CODEPOINT
+        );
+        assert_eq!(
+            decoded.get_attribute::<Icmp>(),
+            Some(&Icmp::new(2, 0, 1280))
+        );
+        Ok(())
+    }
 }
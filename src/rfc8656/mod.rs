@@ -1,6 +1,19 @@
 //! [RFC 8656(Traversal Using Relays around NAT (TURN): Relay Extensions to Session Traversal Utilities for NAT (STUN))][RFC 8656] specific components.
 //!
 //! [RFC 8656]: https://tools.ietf.org/html/rfc8656
+use self::attributes::*;
 
 pub mod attributes;
 pub mod errors;
+
+define_attribute_enums!(
+    Attribute,
+    AttributeDecoder,
+    AttributeEncoder,
+    [
+        RequestedAddressFamily,
+        AdditionalAddressFamily,
+        AddressErrorCode,
+        Icmp
+    ]
+);
@@ -0,0 +1,416 @@
+//! Framing of STUN messages and TURN `ChannelData` messages over stream transports (i.e., TCP and TLS-over-TCP).
+//!
+//! See [RFC 5389 -- 7.2.2. Sending over TCP or TLS-over-TCP] and
+//! [RFC 5766 -- 11.5. Sending a ChannelData Message] about the framing.
+//!
+//! [RFC 5389 -- 7.2.2. Sending over TCP or TLS-over-TCP]: https://tools.ietf.org/html/rfc5389#section-7.2.2
+//! [RFC 5766 -- 11.5. Sending a ChannelData Message]: https://tools.ietf.org/html/rfc5766#section-11.5
+use crate::attribute::padded_len;
+use crate::message::{DecodedMessage, MessageDecoder, MessageHeader};
+use crate::rfc5766::channel_data::{ChannelData, ChannelDataDecoder};
+use crate::Attribute;
+use bytecodec::DecodeExt;
+use std::fmt;
+
+const CHANNEL_DATA_HEADER_LEN: usize = 4;
+
+/// A frame decoded by [`StreamDecoder`].
+#[derive(Debug)]
+pub enum StreamFrame<A> {
+    /// STUN message.
+    Message {
+        /// The decoded message.
+        message: DecodedMessage<A>,
+
+        /// The raw bytes of the message.
+        ///
+        /// They are needed to check the `MESSAGE-INTEGRITY` attribute of the message.
+        bytes: Vec<u8>,
+    },
+
+    /// TURN `ChannelData` message.
+    ChannelData(ChannelData),
+}
+
+/// Decoder that splits a byte stream into STUN messages and TURN `ChannelData` messages.
+///
+/// The boundaries of frames are determined by the length fields of their headers.
+/// Arbitrary chunks of the stream can be passed to [`StreamDecoder::feed`].
+///
+/// If the decoder encounters bytes that cannot be the start of a frame, or a frame that cannot be decoded,
+/// it discards them and resynchronizes with the next frame in the stream.
+/// The number of discarded bytes is available via [`StreamDecoder::discarded_bytes`].
+///
+/// Note that a frame whose length field is corrupted can make the decoder wait for
+/// up to 64 KiB of bytes before it resynchronizes.
+/// Use [`StreamDecoder::with_max_frame_size`] to limit the size of acceptable frames.
+pub struct StreamDecoder<A: Attribute> {
+    buf: Vec<u8>,
+    pos: usize,
+    discarded_bytes: u64,
+    max_frame_size: usize,
+    message: MessageDecoder<A>,
+    channel_data: ChannelDataDecoder,
+}
+impl<A: Attribute> StreamDecoder<A> {
+    /// Makes a new `StreamDecoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes a new `StreamDecoder` instance that treats frames larger than `max_frame_size` bytes
+    /// (including their headers) as garbage.
+    ///
+    /// Such a frame is discarded as soon as its header is received,
+    /// so the decoder does not wait for its body.
+    pub fn with_max_frame_size(max_frame_size: usize) -> Self {
+        StreamDecoder {
+            max_frame_size,
+            ..Self::default()
+        }
+    }
+
+    /// Appends `bytes` to the internal buffer of the decoder.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        // Decoded frames are removed here at once instead of each time a frame is decoded
+        self.buf.drain(..self.pos);
+        self.pos = 0;
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Decodes the next frame from the buffered bytes.
+    ///
+    /// If the buffer does not contain a complete frame, this will return `None`.
+    pub fn next_frame(&mut self) -> Option<StreamFrame<A>> {
+        loop {
+            let buf = &self.buf[self.pos..];
+            let (size, frame) = match FrameBoundary::find(buf, self.max_frame_size) {
+                FrameBoundary::Incomplete => return None,
+                FrameBoundary::Garbage(size) => (size, None),
+                FrameBoundary::Message(size) => {
+                    let result = self.message.decode_from_bytes(&buf[..size]);
+                    if !matches!(result, Ok(Ok(_))) {
+                        // The decoder may have an intermediate state of the broken frame
+                        self.message = MessageDecoder::new();
+                    }
+                    let frame = result.ok().map(|message| StreamFrame::Message {
+                        message,
+                        bytes: buf[..size].to_vec(),
+                    });
+                    (size, frame)
+                }
+                FrameBoundary::ChannelData(size) => {
+                    match self.channel_data.decode_from_bytes(&buf[..size]) {
+                        Ok(data) => (size, Some(StreamFrame::ChannelData(data))),
+                        Err(_) => {
+                            self.channel_data = ChannelDataDecoder::new_stream();
+                            (size, None)
+                        }
+                    }
+                }
+            };
+            self.pos += size;
+            if frame.is_some() {
+                return frame;
+            }
+            self.discarded_bytes += size as u64;
+        }
+    }
+
+    /// Returns the number of bytes that have been fed but not yet decoded.
+    pub fn buffered_bytes(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    /// Returns the total number of bytes that have been discarded to resynchronize the stream.
+    pub fn discarded_bytes(&self) -> u64 {
+        self.discarded_bytes
+    }
+}
+impl<A: Attribute> fmt::Debug for StreamDecoder<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "StreamDecoder {{ buffered_bytes: {}, discarded_bytes: {}, .. }}",
+            self.buffered_bytes(),
+            self.discarded_bytes
+        )
+    }
+}
+impl<A: Attribute> Default for StreamDecoder<A> {
+    fn default() -> Self {
+        StreamDecoder {
+            buf: Vec::new(),
+            pos: 0,
+            discarded_bytes: 0,
+            max_frame_size: usize::MAX,
+            message: MessageDecoder::new(),
+            channel_data: ChannelDataDecoder::new_stream(),
+        }
+    }
+}
+impl<A: Attribute> Iterator for StreamDecoder<A> {
+    type Item = StreamFrame<A>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_frame()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameBoundary {
+    Incomplete,
+    Garbage(usize),
+    Message(usize),
+    ChannelData(usize),
+}
+impl FrameBoundary {
+    fn find(buf: &[u8], max_frame_size: usize) -> Self {
+        let Some(&first) = buf.first() else {
+            return FrameBoundary::Incomplete;
+        };
+        match first {
+            0..=3 => {
+                // Rejects invalid headers as early as possible so as not to wait for bytes in vain
+                if buf.len() < MessageHeader::SIZE {
+                    if MessageHeader::is_plausible_prefix(buf) {
+                        return FrameBoundary::Incomplete;
+                    }
+                    return Self::skip_garbage(buf);
+                }
+                let size = match MessageHeader::peek(buf) {
+                    Ok(header) if header.is_plausible() => header.message_size(),
+                    _ => return Self::skip_garbage(buf),
+                };
+                if size > max_frame_size {
+                    Self::skip_garbage(buf)
+                } else if buf.len() < size {
+                    FrameBoundary::Incomplete
+                } else {
+                    FrameBoundary::Message(size)
+                }
+            }
+            0x40..=0x4F => {
+                if buf.len() < CHANNEL_DATA_HEADER_LEN {
+                    return FrameBoundary::Incomplete;
+                }
+                let len = usize::from(u16::from_be_bytes([buf[2], buf[3]]));
+                let size = CHANNEL_DATA_HEADER_LEN + padded_len(len);
+                if size > max_frame_size {
+                    Self::skip_garbage(buf)
+                } else if buf.len() < size {
+                    FrameBoundary::Incomplete
+                } else {
+                    FrameBoundary::ChannelData(size)
+                }
+            }
+            _ => Self::skip_garbage(buf),
+        }
+    }
+
+    fn skip_garbage(buf: &[u8]) -> Self {
+        let size = buf[1..]
+            .iter()
+            .position(|b| matches!(b, 0..=3 | 0x40..=0x4F))
+            .map_or(buf.len(), |i| i + 1);
+        FrameBoundary::Garbage(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rfc5389::attributes::{MessageIntegrity, Software};
+    use crate::rfc5389::methods::BINDING;
+    use crate::rfc5389::Attribute as Rfc5389Attribute;
+    use crate::rfc5766::attributes::ChannelNumber;
+    use crate::rfc5766::channel_data::ChannelDataEncoder;
+    use crate::{Message, MessageClass, MessageEncoder, TransactionId};
+    use bytecodec::EncodeExt;
+    use trackable::result::TestResult;
+
+    fn message_bytes() -> Vec<u8> {
+        let mut message = Message::<Rfc5389Attribute>::new(
+            MessageClass::Request,
+            BINDING,
+            TransactionId::new([7; 12]),
+        );
+        message.add_attribute(Software::new("foo".to_owned()).expect("never fails"));
+        MessageEncoder::new()
+            .encode_into_bytes(message)
+            .expect("never fails")
+    }
+
+    fn channel_data() -> ChannelData {
+        ChannelData::new(ChannelNumber::min(), b"hello".to_vec()).expect("never fails")
+    }
+
+    fn channel_data_bytes() -> Vec<u8> {
+        ChannelDataEncoder::new_stream()
+            .encode_into_bytes(channel_data())
+            .expect("never fails")
+    }
+
+    fn assert_message(frame: Option<StreamFrame<Rfc5389Attribute>>) {
+        match frame {
+            Some(StreamFrame::Message {
+                message: Ok(message),
+                ..
+            }) => {
+                assert_eq!(message.transaction_id(), TransactionId::new([7; 12]));
+                assert!(message.get_attribute::<Software>().is_some());
+            }
+            frame => panic!("{:?}", frame),
+        }
+    }
+
+    fn assert_channel_data(frame: Option<StreamFrame<Rfc5389Attribute>>) {
+        match frame {
+            Some(StreamFrame::ChannelData(data)) => assert_eq!(data, channel_data()),
+            frame => panic!("{:?}", frame),
+        }
+    }
+
+    #[test]
+    fn stream_decoder_works() -> TestResult {
+        let mut stream = message_bytes();
+        stream.extend_from_slice(&channel_data_bytes());
+        stream.extend_from_slice(&message_bytes());
+
+        let mut decoder = StreamDecoder::<Rfc5389Attribute>::new();
+        let mut frames = Vec::new();
+        for chunk in stream.chunks(5) {
+            decoder.feed(chunk);
+            frames.extend(&mut decoder);
+        }
+        assert_eq!(frames.len(), 3);
+        let mut frames = frames.into_iter();
+        assert_message(frames.next());
+        assert_channel_data(frames.next());
+        assert_message(frames.next());
+        assert_eq!(decoder.buffered_bytes(), 0);
+        assert_eq!(decoder.discarded_bytes(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn stream_decoder_handles_bursts() -> TestResult {
+        let mut decoder = StreamDecoder::<Rfc5389Attribute>::new();
+        let mut stream = Vec::new();
+        for _ in 0..100 {
+            stream.extend_from_slice(&message_bytes());
+        }
+        let (first, rest) = stream.split_at(stream.len() - 10);
+        decoder.feed(first);
+        for _ in 0..99 {
+            assert_message(decoder.next_frame());
+        }
+        assert!(decoder.next_frame().is_none());
+        assert_eq!(decoder.buffered_bytes(), message_bytes().len() - 10);
+
+        decoder.feed(rest);
+        assert_message(decoder.next_frame());
+        assert_eq!(decoder.buffered_bytes(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn stream_message_integrity_can_be_checked() -> TestResult {
+        let mut message = Message::<Rfc5389Attribute>::new(
+            MessageClass::Request,
+            BINDING,
+            TransactionId::new([7; 12]),
+        );
+        message.add_attribute(track!(MessageIntegrity::new_with_key(&message, b"key"))?);
+        let bytes = track!(MessageEncoder::new().encode_into_bytes(message))?;
+
+        let mut decoder = StreamDecoder::<Rfc5389Attribute>::new();
+        decoder.feed(&bytes);
+        decoder.feed(&channel_data_bytes());
+        match decoder.next_frame() {
+            Some(StreamFrame::Message {
+                message: Ok(message),
+                bytes: frame_bytes,
+            }) => {
+                assert_eq!(frame_bytes, bytes);
+                let mi = message
+                    .get_attribute::<MessageIntegrity>()
+                    .expect("never fails");
+                assert!(mi.check_with_key(&frame_bytes, b"key").is_ok());
+                assert!(mi.check_with_key(&frame_bytes, b"foo").is_err());
+            }
+            frame => panic!("{:?}", frame),
+        }
+        assert_channel_data(decoder.next_frame());
+        Ok(())
+    }
+
+    #[test]
+    fn stream_decoder_resynchronizes() -> TestResult {
+        let mut decoder = StreamDecoder::<Rfc5389Attribute>::new();
+
+        // Garbage bytes
+        decoder.feed(&[0xFF, 0x80, 0x10]);
+        decoder.feed(&message_bytes());
+        assert_message(decoder.next_frame());
+        assert_eq!(decoder.discarded_bytes(), 3);
+
+        // STUN-like header with a wrong magic cookie
+        let mut broken = message_bytes();
+        broken[7] ^= 0xFF;
+        decoder.feed(&broken);
+        decoder.feed(&channel_data_bytes());
+        assert_channel_data(decoder.next_frame());
+        assert_eq!(decoder.discarded_bytes(), 3 + broken.len() as u64);
+
+        // STUN frame that has a corrupt attribute
+        let mut broken = message_bytes();
+        broken[22] = 0xFF; // attribute length
+        decoder.feed(&broken);
+        decoder.feed(&message_bytes());
+        match decoder.next_frame() {
+            Some(StreamFrame::Message {
+                message: Err(broken),
+                ..
+            }) => {
+                assert_eq!(broken.transaction_id(), TransactionId::new([7; 12]));
+            }
+            frame => panic!("{:?}", frame),
+        }
+        assert_message(decoder.next_frame());
+        assert!(decoder.next_frame().is_none());
+        assert_eq!(decoder.buffered_bytes(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn stream_decoder_limits_frame_size() -> TestResult {
+        let mut decoder = StreamDecoder::<Rfc5389Attribute>::with_max_frame_size(1024);
+
+        // STUN header with a corrupt length field
+        let mut broken = message_bytes();
+        broken[2] = 0xFF;
+        decoder.feed(&broken);
+        decoder.feed(&message_bytes());
+        assert_message(decoder.next_frame());
+        assert_eq!(decoder.discarded_bytes(), broken.len() as u64);
+
+        // ChannelData header with a corrupt length field
+        let mut broken = channel_data_bytes();
+        broken[2] = 0xFF;
+        decoder.feed(&broken);
+        decoder.feed(&channel_data_bytes());
+        assert_channel_data(decoder.next_frame());
+        assert!(decoder.next_frame().is_none());
+        assert_eq!(decoder.buffered_bytes(), 0);
+
+        // Without the limit, the decoder waits for the body of the frame
+        let mut decoder = StreamDecoder::<Rfc5389Attribute>::new();
+        let mut broken = message_bytes();
+        broken[2] = 0xFF;
+        decoder.feed(&broken);
+        decoder.feed(&message_bytes());
+        assert!(decoder.next_frame().is_none());
+        Ok(())
+    }
+}
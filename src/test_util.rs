@@ -0,0 +1,32 @@
+//! Components shared by the unit tests of this crate.
+#![allow(dead_code)]
+use crate::rfc5389::attributes::{
+    AlternateServer, ErrorCode, Fingerprint, MessageIntegrity, Software, XorMappedAddress,
+};
+use crate::rfc5766::attributes::{Lifetime, RequestedTransport};
+use crate::rfc5780::attributes::{ChangeRequest, OtherAddress, ResponseOrigin, ResponsePort};
+use crate::rfc8016::attributes::MobilityTicket;
+use crate::rfc8489::attributes::{AlternateDomain, MessageIntegritySha256};
+
+define_attribute_enums!(
+    TestAttribute,
+    TestAttributeDecoder,
+    TestAttributeEncoder,
+    [
+        ErrorCode,
+        AlternateServer,
+        AlternateDomain,
+        RequestedTransport,
+        Lifetime,
+        MobilityTicket,
+        XorMappedAddress,
+        ChangeRequest,
+        ResponseOrigin,
+        OtherAddress,
+        ResponsePort,
+        Software,
+        MessageIntegrity,
+        MessageIntegritySha256,
+        Fingerprint
+    ]
+);
@@ -0,0 +1,269 @@
+//! [tokio-util] codec for STUN messages.
+//!
+//! This module is available only if the `tokio` feature is enabled.
+//!
+//! [tokio-util]: https://docs.rs/tokio-util
+use crate::message::{DecodedMessage, Message, MessageDecoder, MessageEncoder, MessageHeader};
+use crate::Attribute;
+use bytecodec::{DecodeExt, Error, ErrorKind};
+use bytes::{Bytes, BytesMut};
+use std::fmt;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Codec that implements [`tokio_util::codec::Decoder`] and [`tokio_util::codec::Encoder`] for STUN messages.
+///
+/// This can be used with both stream transports (e.g., `Framed<TcpStream, StunCodec<A>>`) and
+/// datagram transports (e.g., `UdpFramed<StunCodec<A>>`).
+///
+/// Over streams, the boundaries of messages are determined by the length fields of their headers
+/// (see [`Decoder::decode`]).
+/// The header is checked (e.g., for the magic cookie) as soon as its bytes arrive, before waiting for
+/// the rest of the message.
+/// If it does not look like a STUN message header, the stream cannot be resynchronized, so all of the
+/// buffered bytes are discarded and an `ErrorKind::InvalidInput` error is returned.
+/// Note that `Framed` ends the stream after the decoder returns an error,
+/// so a decode error is terminal for a stream transport.
+///
+/// `UdpFramed` passes each datagram to [`Decoder::decode_eof`], which decodes the whole datagram
+/// as exactly one message.
+/// If the length field of the message does not match the size of the datagram,
+/// the datagram is discarded and an `ErrorKind::InvalidInput` error is returned.
+/// The same applies to the incomplete bytes left at the end of a stream.
+///
+/// Each decoded message is returned along with its raw bytes,
+/// which are needed to check the `MESSAGE-INTEGRITY` attribute of the message.
+///
+/// # Examples
+///
+/// ```
+/// use bytes::BytesMut;
+/// use stun_codec::rfc5389::{methods::BINDING, Attribute};
+/// use stun_codec::tokio::StunCodec;
+/// use stun_codec::{Message, MessageClass, TransactionId};
+/// use tokio_util::codec::{Decoder, Encoder};
+///
+/// let mut codec = StunCodec::<Attribute>::new();
+/// let message = Message::new(MessageClass::Request, BINDING, TransactionId::new([3; 12]));
+///
+/// let mut buf = BytesMut::new();
+/// codec.encode(message, &mut buf).unwrap();
+///
+/// let (decoded, bytes) = codec.decode(&mut buf).unwrap().unwrap();
+/// assert_eq!(decoded.unwrap().transaction_id(), TransactionId::new([3; 12]));
+/// assert_eq!(bytes.len(), 20);
+/// assert!(buf.is_empty());
+/// ```
+pub struct StunCodec<A: Attribute> {
+    decoder: MessageDecoder<A>,
+    encoder: MessageEncoder<A>,
+}
+impl<A: Attribute> StunCodec<A> {
+    /// Makes a new `StunCodec` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn decode_frame(&mut self, frame: Bytes) -> Result<(DecodedMessage<A>, Bytes), Error> {
+        let result = track!(self.decoder.decode_from_bytes(&frame));
+        if !matches!(result, Ok(Ok(_))) {
+            // The decoder may have an intermediate state of the broken frame
+            self.decoder = MessageDecoder::new();
+        }
+        result.map(|message| (message, frame))
+    }
+}
+impl<A: Attribute> fmt::Debug for StunCodec<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "StunCodec {{ .. }}")
+    }
+}
+impl<A: Attribute> Default for StunCodec<A> {
+    fn default() -> Self {
+        StunCodec {
+            decoder: MessageDecoder::new(),
+            encoder: MessageEncoder::new(),
+        }
+    }
+}
+impl<A: Attribute> Decoder for StunCodec<A> {
+    type Item = (DecodedMessage<A>, Bytes);
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if !MessageHeader::is_plausible_prefix(src) {
+            src.clear();
+            track_panic!(ErrorKind::InvalidInput, "Not a STUN message header");
+        }
+        if src.len() < MessageHeader::SIZE {
+            return Ok(None);
+        }
+        let header = match MessageHeader::peek(src) {
+            Ok(header) if header.is_plausible() => header,
+            _ => {
+                src.clear();
+                track_panic!(ErrorKind::InvalidInput, "Not a STUN message header");
+            }
+        };
+
+        let size = header.message_size();
+        if src.len() < size {
+            src.reserve(size - src.len());
+            return Ok(None);
+        }
+
+        let frame = src.split_to(size).freeze();
+        track!(self.decode_frame(frame)).map(Some)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if src.is_empty() {
+            return Ok(None);
+        }
+
+        // The whole bytes are consumed first so that a broken datagram is never decoded again
+        let frame = src.split().freeze();
+        let header = track!(MessageHeader::peek(&frame))?;
+        track_assert!(
+            header.is_plausible(),
+            ErrorKind::InvalidInput,
+            "Not a STUN message header"
+        );
+        track_assert_eq!(
+            header.message_size(),
+            frame.len(),
+            ErrorKind::InvalidInput,
+            "The length field does not match the size of the datagram"
+        );
+        track!(self.decode_frame(frame)).map(Some)
+    }
+}
+impl<A: Attribute> Encoder<Message<A>> for StunCodec<A> {
+    type Error = Error;
+
+    fn encode(&mut self, item: Message<A>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        track!(self.encoder.encode_into_bytes_mut(item, dst))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rfc5389::attributes::{MessageIntegrity, Software};
+    use crate::rfc5389::methods::BINDING;
+    use crate::rfc5389::Attribute as Rfc5389Attribute;
+    use crate::{MessageClass, TransactionId};
+    use trackable::result::TestResult;
+
+    #[test]
+    fn stun_codec_works() -> TestResult {
+        let mut codec = StunCodec::<Rfc5389Attribute>::new();
+        let mut buf = BytesMut::new();
+        for i in 0..2 {
+            let mut message =
+                Message::new(MessageClass::Request, BINDING, TransactionId::new([i; 12]));
+            message.add_attribute(track!(Software::new("foo".to_owned()))?);
+            track!(codec.encode(message, &mut buf))?;
+        }
+
+        // Partial frames
+        let mut src = buf.split_to(10);
+        assert!(track!(codec.decode(&mut src))?.is_none());
+        src.extend_from_slice(&buf.split_to(15));
+        assert!(track!(codec.decode(&mut src))?.is_none());
+        src.unsplit(buf);
+
+        for i in 0..2 {
+            let (message, _) = track!(codec.decode(&mut src))?.expect("never fails");
+            let message = message.expect("never fails");
+            assert_eq!(message.transaction_id(), TransactionId::new([i; 12]));
+            assert!(message.get_attribute::<Software>().is_some());
+        }
+        assert!(src.is_empty());
+        assert!(track!(codec.decode(&mut src))?.is_none());
+
+        // Invalid magic cookie
+        let mut src = BytesMut::from(&[0; 20][..]);
+        assert!(codec.decode(&mut src).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn garbage_prefix_is_rejected_without_waiting_for_the_body() -> TestResult {
+        let mut codec = StunCodec::<Rfc5389Attribute>::new();
+        let message = Message::new(MessageClass::Request, BINDING, TransactionId::new([0; 12]));
+        let mut valid = BytesMut::new();
+        track!(codec.encode(message, &mut valid))?;
+
+        // A garbage header with a large length field
+        let mut src = BytesMut::from(&[0x00, 0x01, 0xFF, 0xFC][..]);
+        src.extend_from_slice(&[0; 16]);
+        assert!(codec.decode(&mut src).is_err());
+        assert!(src.is_empty());
+
+        // Garbage in front of a valid message
+        let mut src = BytesMut::from(&b"GET / HTTP/1.1\r\n"[..]);
+        src.extend_from_slice(&valid);
+        assert!(codec.decode(&mut src).is_err());
+        assert!(src.is_empty());
+
+        // Detected before the whole header arrives
+        let mut src = BytesMut::from(&[0x00, 0x01, 0x00, 0x00, 0xFF][..]);
+        assert!(codec.decode(&mut src).is_err());
+        assert!(src.is_empty());
+
+        // The codec is still usable
+        let mut src = valid.clone();
+        assert!(track!(codec.decode(&mut src))?.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn message_integrity_can_be_checked() -> TestResult {
+        let mut codec = StunCodec::<Rfc5389Attribute>::new();
+        let mut message = Message::new(MessageClass::Request, BINDING, TransactionId::new([0; 12]));
+        message.add_attribute(track!(MessageIntegrity::new_with_key(&message, b"key"))?);
+        let mut buf = BytesMut::new();
+        track!(codec.encode(message, &mut buf))?;
+
+        let (message, bytes) = track!(codec.decode(&mut buf))?.expect("never fails");
+        let message = message.expect("never fails");
+        let mi = message
+            .get_attribute::<MessageIntegrity>()
+            .expect("never fails");
+        assert!(mi.check_with_key(&bytes, b"key").is_ok());
+        assert!(mi.check_with_key(&bytes, b"foo").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn datagrams_are_decoded_by_decode_eof() -> TestResult {
+        let mut codec = StunCodec::<Rfc5389Attribute>::new();
+        let mut message = Message::new(MessageClass::Request, BINDING, TransactionId::new([0; 12]));
+        message.add_attribute(track!(Software::new("foo".to_owned()))?);
+        let mut datagram = BytesMut::new();
+        track!(codec.encode(message, &mut datagram))?;
+
+        let mut src = datagram.clone();
+        let (message, _) = track!(codec.decode_eof(&mut src))?.expect("never fails");
+        assert!(message.is_ok());
+        assert!(track!(codec.decode_eof(&mut src))?.is_none());
+
+        // The length field is shorter than the datagram
+        let mut src = datagram.clone();
+        src.extend_from_slice(&[0; 8]);
+        assert!(codec.decode_eof(&mut src).is_err());
+        assert!(src.is_empty());
+
+        // The length field is longer than the datagram
+        let mut src = datagram.clone();
+        src[3] += 8;
+        assert!(codec.decode_eof(&mut src).is_err());
+        assert!(src.is_empty());
+
+        // The codec is still usable
+        let mut src = datagram.clone();
+        assert!(track!(codec.decode_eof(&mut src))?.is_some());
+        Ok(())
+    }
+}